@@ -1,6 +1,11 @@
 #![allow(dead_code)]
 pub mod lexer {
-    use std::io::{self};
+    use std::borrow::Cow;
+    use std::collections::VecDeque;
+    use std::fmt;
+    use std::io::{self, BufRead};
+    use unicode_ident::{is_xid_continue, is_xid_start};
+    use unicode_normalization::{is_nfc, UnicodeNormalization};
     // 定义关键字枚举类型
     #[derive(Debug, Clone, PartialEq)]
     pub enum Keyword {
@@ -38,10 +43,269 @@ pub mod lexer {
         While,
         True,
         False,
+        // C99
+        Inline,
+        Restrict,
+        Bool,      // _Bool，C23 中也可写作 bool
+        Complex,   // _Complex
+        Imaginary, // _Imaginary
+        // C11
+        Alignas,      // _Alignas，C23 中也可写作 alignas
+        Alignof,      // _Alignof，C23 中也可写作 alignof
+        Atomic,       // _Atomic
+        Generic,      // _Generic
+        Noreturn,     // _Noreturn
+        StaticAssert, // _Static_assert，C23 中也可写作 static_assert
+        ThreadLocal,  // _Thread_local，C23 中也可写作 thread_local
+        // C23
+        Constexpr,
+        Nullptr,
+        Typeof,
+        TypeofUnqual, // typeof_unqual
+        BitInt,       // _BitInt
+        Decimal32,    // _Decimal32
+        Decimal64,    // _Decimal64
+        Decimal128,   // _Decimal128
+        // C++
+        Asm,
+        Catch,
+        Char8T,  // char8_t
+        Char16T, // char16_t
+        Char32T, // char32_t
+        Class,
+        CoAwait,  // co_await
+        CoReturn, // co_return
+        CoYield,  // co_yield
+        Concept,
+        ConstCast, // const_cast
+        Consteval,
+        Constinit,
+        Decltype,
+        Delete,
+        DynamicCast, // dynamic_cast
+        Explicit,
+        Export,
+        Friend,
+        Mutable,
+        Namespace,
+        New,
+        Noexcept,
+        Operator,
+        Private,
+        Protected,
+        Public,
+        ReinterpretCast, // reinterpret_cast
+        Requires,
+        StaticCast, // static_cast
+        Template,
+        This,
+        Throw,
+        Try,
+        Typeid,
+        Typename,
+        Using,
+        Virtual,
+        WcharT, // wchar_t
+    }
+
+    // 各关键字的拼写与引入它的语言标准
+    const KEYWORDS: [(&str, Keyword, LanguageStandard); 59] = [
+        ("auto", Keyword::Auto, LanguageStandard::C89),
+        ("break", Keyword::Break, LanguageStandard::C89),
+        ("case", Keyword::Case, LanguageStandard::C89),
+        ("char", Keyword::Char, LanguageStandard::C89),
+        ("const", Keyword::Const, LanguageStandard::C89),
+        ("continue", Keyword::Continue, LanguageStandard::C89),
+        ("default", Keyword::Default, LanguageStandard::C89),
+        ("do", Keyword::Do, LanguageStandard::C89),
+        ("double", Keyword::Double, LanguageStandard::C89),
+        ("else", Keyword::Else, LanguageStandard::C89),
+        ("enum", Keyword::Enum, LanguageStandard::C89),
+        ("extern", Keyword::Extern, LanguageStandard::C89),
+        ("float", Keyword::Float, LanguageStandard::C89),
+        ("for", Keyword::For, LanguageStandard::C89),
+        ("goto", Keyword::Goto, LanguageStandard::C89),
+        ("if", Keyword::If, LanguageStandard::C89),
+        ("int", Keyword::Int, LanguageStandard::C89),
+        ("long", Keyword::Long, LanguageStandard::C89),
+        ("register", Keyword::Register, LanguageStandard::C89),
+        ("return", Keyword::Return, LanguageStandard::C89),
+        ("short", Keyword::Short, LanguageStandard::C89),
+        ("signed", Keyword::Signed, LanguageStandard::C89),
+        ("sizeof", Keyword::Sizeof, LanguageStandard::C89),
+        ("static", Keyword::Static, LanguageStandard::C89),
+        ("struct", Keyword::Struct, LanguageStandard::C89),
+        ("switch", Keyword::Switch, LanguageStandard::C89),
+        ("typedef", Keyword::Typedef, LanguageStandard::C89),
+        ("union", Keyword::Union, LanguageStandard::C89),
+        ("unsigned", Keyword::Unsigned, LanguageStandard::C89),
+        ("void", Keyword::Void, LanguageStandard::C89),
+        ("volatile", Keyword::Volatile, LanguageStandard::C89),
+        ("while", Keyword::While, LanguageStandard::C89),
+        ("inline", Keyword::Inline, LanguageStandard::C99),
+        ("restrict", Keyword::Restrict, LanguageStandard::C99),
+        ("_Bool", Keyword::Bool, LanguageStandard::C99),
+        ("_Complex", Keyword::Complex, LanguageStandard::C99),
+        ("_Imaginary", Keyword::Imaginary, LanguageStandard::C99),
+        ("_Alignas", Keyword::Alignas, LanguageStandard::C11),
+        ("_Alignof", Keyword::Alignof, LanguageStandard::C11),
+        ("_Atomic", Keyword::Atomic, LanguageStandard::C11),
+        ("_Generic", Keyword::Generic, LanguageStandard::C11),
+        ("_Noreturn", Keyword::Noreturn, LanguageStandard::C11),
+        (
+            "_Static_assert",
+            Keyword::StaticAssert,
+            LanguageStandard::C11,
+        ),
+        ("_Thread_local", Keyword::ThreadLocal, LanguageStandard::C11),
+        ("alignas", Keyword::Alignas, LanguageStandard::C23),
+        ("alignof", Keyword::Alignof, LanguageStandard::C23),
+        ("bool", Keyword::Bool, LanguageStandard::C23),
+        ("constexpr", Keyword::Constexpr, LanguageStandard::C23),
+        ("false", Keyword::False, LanguageStandard::C23),
+        ("nullptr", Keyword::Nullptr, LanguageStandard::C23),
+        (
+            "static_assert",
+            Keyword::StaticAssert,
+            LanguageStandard::C23,
+        ),
+        ("thread_local", Keyword::ThreadLocal, LanguageStandard::C23),
+        ("true", Keyword::True, LanguageStandard::C23),
+        ("typeof", Keyword::Typeof, LanguageStandard::C23),
+        (
+            "typeof_unqual",
+            Keyword::TypeofUnqual,
+            LanguageStandard::C23,
+        ),
+        ("_BitInt", Keyword::BitInt, LanguageStandard::C23),
+        ("_Decimal32", Keyword::Decimal32, LanguageStandard::C23),
+        ("_Decimal64", Keyword::Decimal64, LanguageStandard::C23),
+        ("_Decimal128", Keyword::Decimal128, LanguageStandard::C23),
+    ];
+
+    // C++ 中除 C89 关键字以外的关键字（按 C++20），C99 之后 C 新增的 _Bool、restrict 等不是 C++ 关键字
+    const CPP_KEYWORDS: [(&str, Keyword); 49] = [
+        ("alignas", Keyword::Alignas),
+        ("alignof", Keyword::Alignof),
+        ("asm", Keyword::Asm),
+        ("bool", Keyword::Bool),
+        ("catch", Keyword::Catch),
+        ("char8_t", Keyword::Char8T),
+        ("char16_t", Keyword::Char16T),
+        ("char32_t", Keyword::Char32T),
+        ("class", Keyword::Class),
+        ("co_await", Keyword::CoAwait),
+        ("co_return", Keyword::CoReturn),
+        ("co_yield", Keyword::CoYield),
+        ("concept", Keyword::Concept),
+        ("const_cast", Keyword::ConstCast),
+        ("consteval", Keyword::Consteval),
+        ("constexpr", Keyword::Constexpr),
+        ("constinit", Keyword::Constinit),
+        ("decltype", Keyword::Decltype),
+        ("delete", Keyword::Delete),
+        ("dynamic_cast", Keyword::DynamicCast),
+        ("explicit", Keyword::Explicit),
+        ("export", Keyword::Export),
+        ("false", Keyword::False),
+        ("friend", Keyword::Friend),
+        ("inline", Keyword::Inline),
+        ("mutable", Keyword::Mutable),
+        ("namespace", Keyword::Namespace),
+        ("new", Keyword::New),
+        ("noexcept", Keyword::Noexcept),
+        ("nullptr", Keyword::Nullptr),
+        ("operator", Keyword::Operator),
+        ("private", Keyword::Private),
+        ("protected", Keyword::Protected),
+        ("public", Keyword::Public),
+        ("reinterpret_cast", Keyword::ReinterpretCast),
+        ("requires", Keyword::Requires),
+        ("static_assert", Keyword::StaticAssert),
+        ("static_cast", Keyword::StaticCast),
+        ("template", Keyword::Template),
+        ("this", Keyword::This),
+        ("thread_local", Keyword::ThreadLocal),
+        ("throw", Keyword::Throw),
+        ("true", Keyword::True),
+        ("try", Keyword::Try),
+        ("typeid", Keyword::Typeid),
+        ("typename", Keyword::Typename),
+        ("using", Keyword::Using),
+        ("virtual", Keyword::Virtual),
+        ("wchar_t", Keyword::WcharT),
+    ];
+
+    // C++ 中 and、or 等替代记号是运算符而不是标识符
+    const ALTERNATIVE_TOKENS: [(&str, Operator); 11] = [
+        ("and", Operator::LogicalAnd),
+        ("and_eq", Operator::BitwiseAndAssign),
+        ("bitand", Operator::Amp),
+        ("bitor", Operator::BitwiseOr),
+        ("compl", Operator::BitwiseNot),
+        ("not", Operator::LogicalNot),
+        ("not_eq", Operator::NotEqual),
+        ("or", Operator::LogicalOr),
+        ("or_eq", Operator::BitwiseOrAssign),
+        ("xor", Operator::BitwiseXor),
+        ("xor_eq", Operator::BitwiseXorAssign),
+    ];
+
+    impl Keyword {
+        // 按语言与语言标准查找关键字，拼写必须完全一致，较早的标准中新增的关键字仍是普通标识符；
+        // C++ 不区分标准，按 C++20 的关键字识别
+        pub fn lookup(
+            name: &str,
+            language: Language,
+            standard: LanguageStandard,
+        ) -> Option<Keyword> {
+            Keyword::find(language, standard, |spelling| spelling == name)
+        }
+
+        // 忽略 ASCII 大小写查找关键字，While、INT 也按关键字处理
+        pub fn lookup_ignore_case(
+            name: &str,
+            language: Language,
+            standard: LanguageStandard,
+        ) -> Option<Keyword> {
+            Keyword::find(language, standard, |spelling| {
+                spelling.eq_ignore_ascii_case(name)
+            })
+        }
+
+        fn find(
+            language: Language,
+            standard: LanguageStandard,
+            matches: impl Fn(&str) -> bool,
+        ) -> Option<Keyword> {
+            match language {
+                Language::C => KEYWORDS
+                    .iter()
+                    .find(|(spelling, _, since)| standard >= *since && matches(spelling))
+                    .map(|(_, keyword, _)| keyword.clone()),
+                Language::Cpp => KEYWORDS
+                    .iter()
+                    .filter(|(_, _, since)| *since == LanguageStandard::C89)
+                    .map(|(spelling, keyword, _)| (spelling, keyword))
+                    .chain(
+                        CPP_KEYWORDS
+                            .iter()
+                            .map(|(spelling, keyword)| (spelling, keyword)),
+                    )
+                    .find(|(spelling, _)| matches(spelling))
+                    .map(|(_, keyword)| keyword.clone()),
+            }
+        }
     }
 
     #[derive(Debug, Clone, PartialEq)]
     pub enum Operator {
+        // 词法分析只输出这四个中性的标点符号，一元还是二元由 classify_operators 或语法分析决定
+        Plus,  // +
+        Minus, // -
+        Star,  // *
+        Amp,   // &
+
         // Arithmetic Operators
         Add,      // +
         Subtract, // -
@@ -88,8 +352,16 @@ pub mod lexer {
         // Other Operators
         AddressOf,           // &
         Dereference,         // *
+        UnaryPlus,           // 一元 +
+        Negate,              // 一元 -
         MemberAccess,        // 表示使用 '.' 进行结构体成员访问
         PointerMemberAccess, // 表示使用 '->' 进行指针结构体成员访问
+
+        // C++ Operators
+        ScopeResolution,            // ::，C23 中也用于属性的前缀
+        MemberPointerAccess,        // .*
+        PointerMemberPointerAccess, // ->*
+        ThreeWayCompare,            // <=>
     }
 
     #[derive(Debug, Clone, PartialEq)]
@@ -103,616 +375,2277 @@ pub mod lexer {
         RightBracket,         // ]
         LeftBrace,            // {
         RightBrace,           // }
-        Backslash,            // \
         ConditionalOperator,  // ?
         ConditionalSeparator, // :
+        Hash,                 // #
+        HashHash,             // ##
+        Ellipsis,             // ...
     }
 
     #[derive(Debug, Clone, PartialEq)]
     pub enum Number {
-        Integer(i64), // 整数类型，使用 i64 表示
-        Float(f64),   // 浮点数类型，使用 f64 表示
+        Integer(IntegerConstant), // 整数常量，保留进制、后缀与类型
+        Float(FloatConstant),     // 浮点常量，保留进制与类型
+    }
+
+    // 整数常量的进制
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Radix {
+        Binary,      // 0b
+        Octal,       // 0
+        Decimal,     //
+        Hexadecimal, // 0x
+    }
+
+    impl Radix {
+        pub fn base(&self) -> u32 {
+            match self {
+                Radix::Binary => 2,
+                Radix::Octal => 8,
+                Radix::Decimal => 10,
+                Radix::Hexadecimal => 16,
+            }
+        }
+
+        // 进制前缀的长度，8进制的前导 0 本身也是数字
+        fn prefix_len(&self) -> usize {
+            match self {
+                Radix::Binary | Radix::Hexadecimal => 2,
+                Radix::Octal | Radix::Decimal => 0,
+            }
+        }
+    }
+
+    // 整数后缀，u 与 l 大小写均可，但 ll 必须同为大写或小写
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum IntegerSuffix {
+        None,
+        Unsigned,         // u
+        Long,             // l
+        UnsignedLong,     // ul lu
+        LongLong,         // ll
+        UnsignedLongLong, // ull llu
+    }
+
+    impl IntegerSuffix {
+        fn parse(text: &str) -> Option<IntegerSuffix> {
+            let (unsigned_first, rest) = match text.strip_prefix(['u', 'U']) {
+                Some(rest) => (true, rest),
+                None => (false, text),
+            };
+            let (length, rest) = if let Some(rest) = rest.strip_prefix("ll") {
+                (2, rest)
+            } else if let Some(rest) = rest.strip_prefix("LL") {
+                (2, rest)
+            } else if let Some(rest) = rest.strip_prefix(['l', 'L']) {
+                (1, rest)
+            } else {
+                (0, rest)
+            };
+            let unsigned = match rest {
+                "" => unsigned_first,
+                "u" | "U" if !unsigned_first && length > 0 => true,
+                _ => return None,
+            };
+            Some(match (unsigned, length) {
+                (false, 0) => IntegerSuffix::None,
+                (true, 0) => IntegerSuffix::Unsigned,
+                (false, 1) => IntegerSuffix::Long,
+                (true, 1) => IntegerSuffix::UnsignedLong,
+                (false, _) => IntegerSuffix::LongLong,
+                (true, _) => IntegerSuffix::UnsignedLongLong,
+            })
+        }
+    }
+
+    // 整数常量的类型，按 LP64 计算：int 为 32 位，long 与 long long 为 64 位
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum IntegerType {
+        Int,
+        UnsignedInt,
+        Long,
+        UnsignedLong,
+        LongLong,
+        UnsignedLongLong,
+    }
+
+    impl IntegerType {
+        pub fn max_value(&self) -> u64 {
+            match self {
+                IntegerType::Int => i32::MAX as u64,
+                IntegerType::UnsignedInt => u32::MAX as u64,
+                IntegerType::Long | IntegerType::LongLong => i64::MAX as u64,
+                IntegerType::UnsignedLong | IntegerType::UnsignedLongLong => u64::MAX,
+            }
+        }
+
+        // 按 C17 6.4.4.1 的表格，取第一个能表示该值的类型
+        fn of(value: u64, radix: Radix, suffix: IntegerSuffix) -> Option<IntegerType> {
+            use IntegerType::*;
+            let decimal = radix == Radix::Decimal;
+            let candidates: &[IntegerType] = match suffix {
+                IntegerSuffix::None if decimal => &[Int, Long, LongLong],
+                IntegerSuffix::None => &[
+                    Int,
+                    UnsignedInt,
+                    Long,
+                    UnsignedLong,
+                    LongLong,
+                    UnsignedLongLong,
+                ],
+                IntegerSuffix::Unsigned => &[UnsignedInt, UnsignedLong, UnsignedLongLong],
+                IntegerSuffix::Long if decimal => &[Long, LongLong],
+                IntegerSuffix::Long => &[Long, UnsignedLong, LongLong, UnsignedLongLong],
+                IntegerSuffix::UnsignedLong => &[UnsignedLong, UnsignedLongLong],
+                IntegerSuffix::LongLong if decimal => &[LongLong],
+                IntegerSuffix::LongLong => &[LongLong, UnsignedLongLong],
+                IntegerSuffix::UnsignedLongLong => &[UnsignedLongLong],
+            };
+            candidates
+                .iter()
+                .copied()
+                .find(|ty| value <= ty.max_value())
+        }
+    }
+
+    // 浮点常量的类型，由后缀决定：无后缀为 double，f/F 为 float，l/L 为 long double
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum FloatType {
+        Float,
+        Double,
+        LongDouble,
+    }
+
+    impl FloatType {
+        fn from_suffix(text: &str) -> Option<FloatType> {
+            match text {
+                "" => Some(FloatType::Double),
+                "f" | "F" => Some(FloatType::Float),
+                "l" | "L" => Some(FloatType::LongDouble),
+                _ => None,
+            }
+        }
     }
 
+    // 浮点常量，radix 为十进制或16进制；float 类型的值已舍入到单精度，long double 仍用 f64 近似
     #[derive(Debug, Clone, PartialEq)]
-    pub enum Token {
+    pub struct FloatConstant {
+        pub value: f64,
+        pub radix: Radix,
+        pub ty: FloatType,
+    }
+
+    // 整数常量，value 为数值；常量本身不带符号，-1 是一元 - 与常量 1
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct IntegerConstant {
+        pub value: u64,
+        pub radix: Radix,
+        pub suffix: IntegerSuffix,
+        pub ty: IntegerType,
+    }
+
+    // 输入所遵循的语言标准，影响词法上可接受的写法
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+    pub enum LanguageStandard {
+        C89,
+        C99,
+        C11,
+        #[default]
+        C17,
+        C23,
+    }
+
+    // 输入的语言，C++ 的关键字、运算符与字面量写法与 C 不同
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum Language {
+        #[default]
+        C,
+        Cpp,
+    }
+
+    // 字符常量与字符串字面量的编码前缀
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Encoding {
+        Plain, // 无前缀
+        Wide,  // L
+        Utf8,  // u8
+        Utf16, // u
+        Utf32, // U
+    }
+
+    impl Encoding {
+        pub fn prefix(&self) -> &'static str {
+            match self {
+                Encoding::Plain => "",
+                Encoding::Wide => "L",
+                Encoding::Utf8 => "u8",
+                Encoding::Utf16 => "u",
+                Encoding::Utf32 => "U",
+            }
+        }
+
+        fn from_prefix(prefix: &str) -> Option<Encoding> {
+            match prefix {
+                "L" => Some(Encoding::Wide),
+                "u8" => Some(Encoding::Utf8),
+                "u" => Some(Encoding::Utf16),
+                "U" => Some(Encoding::Utf32),
+                _ => None,
+            }
+        }
+
+        // 单个编码单元能表示的最大值，wchar_t 按 32 位处理
+        fn max_unit(&self) -> u64 {
+            match self {
+                Encoding::Plain | Encoding::Utf8 => 0xff,
+                Encoding::Utf16 => 0xffff,
+                Encoding::Wide | Encoding::Utf32 => 0xffff_ffff,
+            }
+        }
+
+        // 将字符按该编码转换为编码单元
+        fn encode(&self, units: &mut Vec<u32>, ch: char) {
+            match self {
+                Encoding::Plain | Encoding::Utf8 => {
+                    let mut buffer = [0u8; 4];
+                    units.extend(ch.encode_utf8(&mut buffer).bytes().map(|byte| byte as u32));
+                }
+                Encoding::Utf16 => {
+                    let mut buffer = [0u16; 2];
+                    units.extend(ch.encode_utf16(&mut buffer).iter().map(|&unit| unit as u32));
+                }
+                Encoding::Wide | Encoding::Utf32 => units.push(ch as u32),
+            }
+        }
+    }
+
+    // 字符常量，raw 为包含前缀和引号的原始文本，value 为按 C 规则计算出的值
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct CharLiteral<'src> {
+        pub encoding: Encoding,
+        pub raw: Cow<'src, str>,
+        pub value: i64,
+    }
+
+    // 字符串字面量，raw 为包含前缀和引号的原始文本，value 为处理转义后的编码单元
+    // （窄字符串与 u8 为 UTF-8 字节，u 为 UTF-16，U 与 L 为 UTF-32）
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct StringLiteral<'src> {
+        pub encoding: Encoding,
+        pub raw: Cow<'src, str>,
+        pub value: Vec<u32>,
+    }
+
+    impl StringLiteral<'_> {
+        // 将处理转义后的内容转换为字符串，无法解码的部分替换为 U+FFFD
+        pub fn to_string_lossy(&self) -> String {
+            match self.encoding {
+                Encoding::Plain | Encoding::Utf8 => {
+                    let bytes: Vec<u8> = self.value.iter().map(|&unit| unit as u8).collect();
+                    String::from_utf8_lossy(&bytes).into_owned()
+                }
+                Encoding::Utf16 => {
+                    let units: Vec<u16> = self.value.iter().map(|&unit| unit as u16).collect();
+                    String::from_utf16_lossy(&units)
+                }
+                Encoding::Wide | Encoding::Utf32 => self
+                    .value
+                    .iter()
+                    .map(|&unit| char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect(),
+            }
+        }
+    }
+
+    // C++ 的用户定义字面量，literal 为去掉后缀的数字、字符或字符串常量，suffix 为 _km 这样的后缀
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct UserDefinedLiteral<'src> {
+        pub literal: Box<Token<'src>>,
+        pub suffix: Cow<'src, str>,
+    }
+
+    // 标识符与字符串直接借用输入中的切片，需要脱离输入时可调用 into_owned
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Token<'src> {
         Keywords(Keyword),
-        Identifiers(String),
+        Identifiers(Cow<'src, str>),
         Numbers(Number),
         Operators(Operator),
         Delimiters(Delimiter),
-        Strings(String),
-        EOF
+        Strings(StringLiteral<'src>),
+        CharConstant(CharLiteral<'src>),
+        Directive,                                    // 行首的 #，开始一条预处理指令
+        EndOfDirective,                               // 结束预处理指令的换行
+        HeaderName(Cow<'src, str>), // #include 后的 <stdio.h> 或 "foo.h"，包含两侧的括号或引号
+        UserDefinedLiteral(UserDefinedLiteral<'src>), // C++ 的 123_km、"abc"s 等
+        Error,                      // 格式错误的常量，错误另行报告，Span 覆盖整个常量
+        EOF,
+    }
+
+    impl Token<'_> {
+        pub fn into_owned(self) -> Token<'static> {
+            match self {
+                Token::Keywords(keyword) => Token::Keywords(keyword),
+                Token::Identifiers(name) => Token::Identifiers(Cow::Owned(name.into_owned())),
+                Token::Numbers(number) => Token::Numbers(number),
+                Token::Operators(operator) => Token::Operators(operator),
+                Token::Delimiters(delimiter) => Token::Delimiters(delimiter),
+                Token::Strings(literal) => Token::Strings(StringLiteral {
+                    encoding: literal.encoding,
+                    raw: Cow::Owned(literal.raw.into_owned()),
+                    value: literal.value,
+                }),
+                Token::CharConstant(literal) => Token::CharConstant(CharLiteral {
+                    encoding: literal.encoding,
+                    raw: Cow::Owned(literal.raw.into_owned()),
+                    value: literal.value,
+                }),
+                Token::Directive => Token::Directive,
+                Token::EndOfDirective => Token::EndOfDirective,
+                Token::HeaderName(name) => Token::HeaderName(Cow::Owned(name.into_owned())),
+                Token::UserDefinedLiteral(literal) => {
+                    Token::UserDefinedLiteral(UserDefinedLiteral {
+                        literal: Box::new(literal.literal.into_owned()),
+                        suffix: Cow::Owned(literal.suffix.into_owned()),
+                    })
+                }
+                Token::Error => Token::Error,
+                Token::EOF => Token::EOF,
+            }
+        }
+
+        // C++ 模板实参列表中的 > 会与后面的字符组成 >>、>=、>>=，语法分析需要结束模板时拆出开头的 >，
+        // 返回拆开后的两个 token 及其 Span，其他 token 返回 None
+        pub fn split_greater_than(
+            &self,
+            span: Span,
+        ) -> Option<((Token<'static>, Span), (Token<'static>, Span))> {
+            let rest = match self {
+                Token::Operators(Operator::RightShift) => Operator::GreaterThan,
+                Token::Operators(Operator::GreaterThanOrEqual) => Operator::Assign,
+                Token::Operators(Operator::RightShiftAssign) => Operator::GreaterThanOrEqual,
+                _ => return None,
+            };
+            let middle = span.start + 1;
+            Some((
+                (
+                    Token::Operators(Operator::GreaterThan),
+                    Span::new(span.file_id, span.start, middle),
+                ),
+                (
+                    Token::Operators(rest),
+                    Span::new(span.file_id, middle, span.end),
+                ),
+            ))
+        }
+    }
+
+    // classify_operators 中尚未闭合的 (
+    struct Group {
+        cast: bool,    // 到目前为止只含类型名与 *，闭合后可能是类型转换
+        types: bool,   // 是否含有类型名
+        control: bool, // if、while、for、switch 的条件，闭合后开始一条语句
+    }
+
+    // 可以出现在类型转换的类型名中的关键字
+    fn type_keyword(keyword: &Keyword) -> bool {
+        matches!(
+            keyword,
+            Keyword::Void
+                | Keyword::Char
+                | Keyword::Short
+                | Keyword::Int
+                | Keyword::Long
+                | Keyword::Float
+                | Keyword::Double
+                | Keyword::Signed
+                | Keyword::Unsigned
+                | Keyword::Const
+                | Keyword::Volatile
+                | Keyword::Restrict
+                | Keyword::Struct
+                | Keyword::Union
+                | Keyword::Enum
+                | Keyword::Bool
+                | Keyword::Complex
+                | Keyword::Imaginary
+                | Keyword::Atomic
+                | Keyword::Decimal32
+                | Keyword::Decimal64
+                | Keyword::Decimal128
+                | Keyword::Char8T
+                | Keyword::Char16T
+                | Keyword::Char32T
+                | Keyword::WcharT
+                | Keyword::Class
+                | Keyword::Typename
+        )
+    }
+
+    // 按上下文为 + - * & 确定一元或二元的含义：前一个 token 是操作数的结尾时为二元运算符，否则为一元运算符。
+    // is_type_name 判断标识符是否为 typedef 定义的类型名，用来识别 (int)*p、(T)-x 这样的类型转换，
+    // 类型转换与 if、while 等的条件之后不是操作数的结尾；sizeof(int) 这样的括号仍是操作数。
+    // 类型名之后的 * 是指针声明符，如 int *p，不是运算符，保留为 Star
+    pub fn classify_operators(tokens: &mut [Token<'_>], is_type_name: &dyn Fn(&str) -> bool) {
+        let mut after_operand = false;
+        let mut groups: Vec<Group> = Vec::new();
+        let mut previous: Option<Keyword> = None; // 上一个 token 是关键字时为该关键字
+        let mut declarator = false; // 是否位于类型名之后，如 int *、int **、void (*
+        for token in tokens.iter_mut() {
+            // struct、union、enum 之后的标识符是标记名
+            let type_name = match &*token {
+                Token::Keywords(keyword) => type_keyword(keyword),
+                Token::Identifiers(name) => {
+                    is_type_name(name)
+                        || matches!(
+                            previous,
+                            Some(Keyword::Struct | Keyword::Union | Keyword::Enum)
+                        )
+                }
+                _ => false,
+            };
+            let parenthesis = matches!(
+                token,
+                Token::Delimiters(Delimiter::LeftParenthesis | Delimiter::RightParenthesis)
+            );
+            if let Some(group) = groups.last_mut().filter(|_| !parenthesis) {
+                group.types |= type_name;
+                group.cast &= type_name || *token == Token::Operators(Operator::Star);
+            }
+            let in_declarator = declarator;
+            declarator = type_name;
+            after_operand = match token {
+                Token::Operators(Operator::Star) if in_declarator => {
+                    declarator = true;
+                    false
+                }
+                Token::Operators(operator) => {
+                    let role = match (&*operator, after_operand) {
+                        (Operator::Plus, true) => Some(Operator::Add),
+                        (Operator::Plus, false) => Some(Operator::UnaryPlus),
+                        (Operator::Minus, true) => Some(Operator::Subtract),
+                        (Operator::Minus, false) => Some(Operator::Negate),
+                        (Operator::Star, true) => Some(Operator::Multiply),
+                        (Operator::Star, false) => Some(Operator::Dereference),
+                        (Operator::Amp, true) => Some(Operator::BitwiseAnd),
+                        (Operator::Amp, false) => Some(Operator::AddressOf),
+                        _ => None,
+                    };
+                    if let Some(role) = role {
+                        *operator = role;
+                    }
+                    // 后缀 ++ 与 -- 之后仍是操作数的结尾，前缀的则不是
+                    matches!(operator, Operator::Increment | Operator::Decrement) && after_operand
+                }
+                Token::Delimiters(Delimiter::LeftParenthesis) => {
+                    // 类型名中的括号，如 (int (*)(void))
+                    declarator = in_declarator;
+                    let nested = groups.last().is_some_and(|group| group.cast);
+                    let operand = matches!(
+                        previous,
+                        Some(
+                            Keyword::Sizeof
+                                | Keyword::Alignof
+                                | Keyword::Typeof
+                                | Keyword::TypeofUnqual
+                                | Keyword::Decltype
+                                | Keyword::Typeid
+                        )
+                    );
+                    groups.push(Group {
+                        cast: nested || (!after_operand && !operand),
+                        types: false,
+                        control: matches!(
+                            previous,
+                            Some(Keyword::If | Keyword::While | Keyword::For | Keyword::Switch)
+                        ),
+                    });
+                    false
+                }
+                Token::Delimiters(Delimiter::RightParenthesis) => match groups.pop() {
+                    Some(group) => {
+                        if let Some(outer) = groups.last_mut() {
+                            outer.cast &= group.cast;
+                            outer.types |= group.types;
+                        }
+                        !(group.control || group.cast && group.types)
+                    }
+                    None => true,
+                },
+                Token::Identifiers(_) => !type_name,
+                Token::Numbers(_)
+                | Token::Strings(_)
+                | Token::CharConstant(_)
+                | Token::UserDefinedLiteral(_)
+                | Token::Error
+                | Token::Keywords(
+                    Keyword::True | Keyword::False | Keyword::Nullptr | Keyword::This,
+                )
+                | Token::Delimiters(Delimiter::RightBracket) => true,
+                _ => false,
+            };
+            previous = match token {
+                Token::Keywords(keyword) => Some(keyword.clone()),
+                _ => None,
+            };
+        }
+    }
+
+    // 源码区间，start/end 为输入中的字节偏移（左闭右开），file_id 标识所属文件
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct Span {
+        pub file_id: usize,
+        pub start: usize,
+        pub end: usize,
+    }
+
+    impl Span {
+        pub fn new(file_id: usize, start: usize, end: usize) -> Self {
+            Span {
+                file_id,
+                start,
+                end,
+            }
+        }
+
+        pub fn len(&self) -> usize {
+            self.end - self.start
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.start == self.end
+        }
+
+        // 合并两个区间，得到覆盖二者的最小区间
+        pub fn to(&self, other: Span) -> Span {
+            Span {
+                file_id: self.file_id,
+                start: self.start.min(other.start),
+                end: self.end.max(other.end),
+            }
+        }
+
+        // 从原始输入中截取该区间对应的源码文本
+        pub fn source_text<'a>(&self, source: &'a str) -> &'a str {
+            &source[self.start..self.end]
+        }
+    }
+
+    // 行列位置由 Span 按需推导，row 与 col 均从 1 开始，col 以字节计
+    #[derive(Debug, Clone)]
+    pub struct TokenLocation {
+        row: usize,
+        col: usize,
+        span: Span,
+    }
+
+    impl TokenLocation {
+        pub fn row(&self) -> usize {
+            self.row
+        }
+
+        pub fn col(&self) -> usize {
+            self.col
+        }
+
+        pub fn span(&self) -> Span {
+            self.span
+        }
+    }
+
+    // 诊断的严重程度
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Severity {
+        Error,
+        Warning,
+    }
+
+    // 词法错误，每种错误都带有出错位置，错误码保持稳定，便于过滤与统计
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum LexError {
+        UnknownCharacter { ch: char, span: Span }, // 无法识别的字符
+        MalformedFloat(Span),                      // 小数点后缺少数字
+        MalformedExponent(Span),                   // 指数部分缺少数字
+        InvalidOctalDigit(Span),                   // 8进制数中出现8或9
+        MissingHexDigits(Span),                    // 0x 后缺少16进制数字
+        MissingBinaryDigits(Span),                 // 0b 后缺少2进制数字
+        IntegerOverflow(Span),                     // 整数超出可表示范围
+        UnterminatedChar(Span),                    // 单引号未闭合
+        UnterminatedString(Span),                  // 双引号未闭合
+        EmptyCharConstant(Span),                   // 空字符常量 ''
+        MultiCharConstant(Span),                   // 多字符常量，值由实现定义
+        UnknownEscape { ch: char, span: Span },    // 未知的转义序列，按字符本身处理
+        MissingHexEscapeDigits(Span),              // \x 后缺少16进制数字
+        EscapeOutOfRange(Span),                    // 转义序列的值超出字符类型范围
+        InvalidUniversalCharacter(Span),           // \u 或 \U 不是合法的通用字符名
+        InvalidIntegerSuffix(Span),                // 整数后缀不合法
+        IntegerTooLargeForSigned(Span),            // 十进制常量超出有符号范围，按无符号处理
+        MissingBinaryExponent(Span),               // 16进制浮点数缺少 p 指数
+        InvalidFloatSuffix(Span),                  // 浮点数后缀不合法
+        InvalidDigitSeparator(Span),               // 数字分隔符 ' 不在两个数字之间
+        InvalidIdentifierCharacter { ch: char, span: Span }, // 不符合 UAX #31 的标识符字符
+        InvalidRawStringDelimiter(Span),           // 原始字符串的分隔符过长或含有非法字符
+        UnterminatedComment(Span),                 // /* 注释未结束，Span 指向开头的 /*
+    }
+
+    impl LexError {
+        pub fn code(&self) -> &'static str {
+            match self {
+                LexError::UnknownCharacter { .. } => "E0001",
+                LexError::MalformedFloat(_) => "E0002",
+                LexError::MalformedExponent(_) => "E0003",
+                LexError::InvalidOctalDigit(_) => "E0004",
+                LexError::MissingHexDigits(_) => "E0005",
+                LexError::MissingBinaryDigits(_) => "E0006",
+                LexError::IntegerOverflow(_) => "E0007",
+                LexError::UnterminatedChar(_) => "E0008",
+                LexError::UnterminatedString(_) => "E0009",
+                LexError::EmptyCharConstant(_) => "E0010",
+                LexError::MultiCharConstant(_) => "W0011",
+                LexError::UnknownEscape { .. } => "W0012",
+                LexError::MissingHexEscapeDigits(_) => "E0013",
+                LexError::EscapeOutOfRange(_) => "E0014",
+                LexError::InvalidUniversalCharacter(_) => "E0015",
+                LexError::InvalidIntegerSuffix(_) => "E0016",
+                LexError::IntegerTooLargeForSigned(_) => "W0017",
+                LexError::MissingBinaryExponent(_) => "E0018",
+                LexError::InvalidFloatSuffix(_) => "E0019",
+                LexError::InvalidDigitSeparator(_) => "E0020",
+                LexError::InvalidIdentifierCharacter { .. } => "E0021",
+                LexError::InvalidRawStringDelimiter(_) => "E0022",
+                LexError::UnterminatedComment(_) => "E0023",
+            }
+        }
+
+        pub fn span(&self) -> Span {
+            match self {
+                LexError::UnknownCharacter { span, .. }
+                | LexError::UnknownEscape { span, .. }
+                | LexError::InvalidIdentifierCharacter { span, .. } => *span,
+                LexError::MalformedFloat(span)
+                | LexError::MalformedExponent(span)
+                | LexError::InvalidOctalDigit(span)
+                | LexError::MissingHexDigits(span)
+                | LexError::MissingBinaryDigits(span)
+                | LexError::IntegerOverflow(span)
+                | LexError::UnterminatedChar(span)
+                | LexError::UnterminatedString(span)
+                | LexError::EmptyCharConstant(span)
+                | LexError::MultiCharConstant(span)
+                | LexError::MissingHexEscapeDigits(span)
+                | LexError::EscapeOutOfRange(span)
+                | LexError::InvalidUniversalCharacter(span)
+                | LexError::InvalidIntegerSuffix(span)
+                | LexError::IntegerTooLargeForSigned(span)
+                | LexError::MissingBinaryExponent(span)
+                | LexError::InvalidFloatSuffix(span)
+                | LexError::InvalidDigitSeparator(span)
+                | LexError::InvalidRawStringDelimiter(span)
+                | LexError::UnterminatedComment(span) => *span,
+            }
+        }
+
+        pub fn severity(&self) -> Severity {
+            match self {
+                LexError::MultiCharConstant(_)
+                | LexError::UnknownEscape { .. }
+                | LexError::IntegerTooLargeForSigned(_) => Severity::Warning,
+                _ => Severity::Error,
+            }
+        }
+    }
+
+    impl fmt::Display for LexError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let message = match self {
+                LexError::UnknownCharacter { ch, .. } => format!("unknown character {:?}", ch),
+                LexError::MalformedFloat(_) => "expected digits after decimal point".to_string(),
+                LexError::MalformedExponent(_) => "exponent has no digits".to_string(),
+                LexError::InvalidOctalDigit(_) => "invalid digit in octal constant".to_string(),
+                LexError::MissingHexDigits(_) => "hexadecimal constant has no digits".to_string(),
+                LexError::MissingBinaryDigits(_) => "binary constant has no digits".to_string(),
+                LexError::IntegerOverflow(_) => "integer constant is too large".to_string(),
+                LexError::UnterminatedChar(_) => "missing terminating ' character".to_string(),
+                LexError::UnterminatedString(_) => "missing terminating \" character".to_string(),
+                LexError::EmptyCharConstant(_) => "empty character constant".to_string(),
+                LexError::MultiCharConstant(_) => "multi-character character constant".to_string(),
+                LexError::UnknownEscape { ch, .. } => format!("unknown escape sequence '\\{}'", ch),
+                LexError::MissingHexEscapeDigits(_) => {
+                    "\\x used with no following hex digits".to_string()
+                }
+                LexError::EscapeOutOfRange(_) => "escape sequence out of range".to_string(),
+                LexError::InvalidUniversalCharacter(_) => {
+                    "invalid universal character name".to_string()
+                }
+                LexError::InvalidIntegerSuffix(_) => {
+                    "invalid suffix on integer constant".to_string()
+                }
+                LexError::IntegerTooLargeForSigned(_) => {
+                    "integer constant is so large that it is unsigned".to_string()
+                }
+                LexError::MissingBinaryExponent(_) => {
+                    "hexadecimal floating constant requires an exponent".to_string()
+                }
+                LexError::InvalidFloatSuffix(_) => {
+                    "invalid suffix on floating constant".to_string()
+                }
+                LexError::InvalidDigitSeparator(_) => {
+                    "digit separator must appear between digits".to_string()
+                }
+                LexError::InvalidIdentifierCharacter { ch, .. } => {
+                    format!("character {:?} is not allowed in an identifier", ch)
+                }
+                LexError::InvalidRawStringDelimiter(_) => {
+                    "invalid delimiter in raw string literal".to_string()
+                }
+                LexError::UnterminatedComment(_) => "unterminated comment".to_string(),
+            };
+            write!(f, "{} [{}]", message, self.code())
+        }
+    }
+
+    impl std::error::Error for LexError {}
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum State {
+        Start,   //开始匹配
+        State1,  //开始->字母或下划线
+        State11, //State1->字母或数字或下划线，以及通用字符名与非 ASCII 字符
+        State2,  //开始->数字
+        State20,
+        State21,
+        State22,
+        State23,
+        State231, // 指数符号之后必须跟数字
+        State24,
+        State25,
+        State26,
+        State261,
+        State262, // 16进制浮点数的小数部分
+        State27,  //匹配二进制
+        State271, //二进制数字
+        State28,  //匹配8进制
+        State281, //8进制异常处理
+        // State29, // 非多个e或是小数点的错误处理
+        State3,  //开始->运算符
+        State31, // State31可匹配<<=
+        State32, // State32可匹配>>=
+        State33, //处理注释/* */
+        State4,  //开始->界符
+        State41, //匹配单引号
+        State42, //匹配双引号
+    }
+
+    // 解析去掉 0x 前缀的16进制浮点数，如 1.8p3，尾数至少要有一位数字
+    fn parse_hex_float(text: &str) -> Option<f64> {
+        let (mantissa, exponent) = text.split_once(['p', 'P'])?;
+        let mut value = 0.0;
+        let mut scale = 0;
+        let mut has_digit = false;
+        let mut fraction = false;
+        for c in mantissa.chars() {
+            if c == '.' {
+                fraction = true;
+                continue;
+            }
+            value = value * 16.0 + c.to_digit(16)? as f64;
+            has_digit = true;
+            if fraction {
+                scale -= 4;
+            }
+        }
+        if !has_digit {
+            return None;
+        }
+        let exponent = exponent.parse::<i32>().ok()?;
+        Some(value * 2f64.powi(exponent.saturating_add(scale)))
+    }
+
+    // 三字符组 ??x 替换成的字符
+    fn trigraph(c: u8) -> Option<u8> {
+        let replacement = match c {
+            b'=' => b'#',
+            b'(' => b'[',
+            b'/' => b'\\',
+            b')' => b']',
+            b'\'' => b'^',
+            b'<' => b'{',
+            b'!' => b'|',
+            b'>' => b'}',
+            b'-' => b'~',
+            _ => return None,
+        };
+        Some(replacement)
+    }
+
+    // 对一段源码执行三字符组替换与续行，得到 token 在预处理中使用的拼写，没有需要翻译的内容时直接借用
+    pub fn spelling(text: &str, trigraphs: bool) -> Cow<'_, str> {
+        let mut translation = Translation::new(trigraphs);
+        if !translation.needed(text) {
+            return Cow::Borrowed(text);
+        }
+        let mut output = Vec::with_capacity(text.len());
+        translation.translate(text.as_bytes(), &mut output, true);
+        Cow::Owned(String::from_utf8_lossy(&output).into_owned())
+    }
+
+    // 只记录偏移的对应关系、不保存翻译结果时使用的输出
+    struct Discard;
+
+    impl Extend<u8> for Discard {
+        fn extend<T: IntoIterator<Item = u8>>(&mut self, _: T) {}
+    }
+
+    // 逻辑偏移 logical 之前的字符在物理偏移 before 处结束，logical 处的字符从物理偏移 after 开始
+    #[derive(Debug, Clone, Copy)]
+    struct Gap {
+        logical: usize,
+        before: usize,
+        after: usize,
+    }
+
+    // 翻译阶段 1、2：可选的三字符组替换，以及删除反斜杠加换行把物理行拼接为逻辑行。
+    // 状态机处理翻译后的逻辑输入，输出的 Span 通过 gaps 换算回物理输入中的偏移，行列也因此对应物理行
+    #[derive(Debug, Clone, Default)]
+    struct Translation {
+        trigraphs: bool,
+        gaps: Vec<Gap>,  // 按 logical 递增，物理与逻辑偏移之差在这些位置发生变化
+        physical: usize, // 已经翻译的物理输入长度
+        logical: usize,  // 已经输出的逻辑输入长度
+    }
+
+    impl Translation {
+        fn new(trigraphs: bool) -> Self {
+            Translation {
+                trigraphs,
+                ..Translation::default()
+            }
+        }
+
+        // 输入中是否有需要翻译的内容
+        fn needed(&self, input: &str) -> bool {
+            input.contains("\\\n")
+                || input.contains("\\\r\n")
+                || self.trigraphs && input.contains("??")
+        }
+
+        // 翻译 input 并追加到 output，返回消耗的字节数；
+        // eof 为 false 时末尾可能是不完整的三字符组或续行，暂不翻译，留到读入更多输入后再处理
+        fn translate(&mut self, input: &[u8], output: &mut impl Extend<u8>, eof: bool) -> usize {
+            let mut i = 0;
+            while i < input.len() {
+                let (c, width) = match input[i] {
+                    b'?' if self.trigraphs => {
+                        if i + 2 >= input.len() && !eof {
+                            break;
+                        }
+                        let replacement = input
+                            .get(i + 2)
+                            .filter(|_| input[i + 1] == b'?')
+                            .and_then(|&c| trigraph(c));
+                        match replacement {
+                            Some(c) => (c, 3),
+                            None => (b'?', 1),
+                        }
+                    }
+                    c => (c, 1),
+                };
+                if c == b'\\' {
+                    // 反斜杠紧跟换行（包括 \r\n）时删除二者，??/ 替换成的反斜杠同样如此
+                    let newline = match (input.get(i + width), input.get(i + width + 1)) {
+                        (Some(b'\n'), _) => Some(1),
+                        (Some(b'\r'), Some(b'\n')) => Some(2),
+                        (None, _) | (Some(b'\r'), None) if !eof => break,
+                        _ => None,
+                    };
+                    if let Some(newline) = newline {
+                        let before = self.physical;
+                        i += width + newline;
+                        self.physical += width + newline;
+                        self.add_gap(before);
+                        continue;
+                    }
+                }
+                output.extend([c]);
+                i += width;
+                self.physical += width;
+                self.logical += 1;
+                if width > 1 {
+                    self.add_gap(self.physical);
+                }
+            }
+            i
+        }
+
+        // 在当前逻辑位置记录偏移的变化，before 为此前字符的物理结束位置；
+        // 连续的续行合并为一处，保留最早的 before
+        fn add_gap(&mut self, before: usize) {
+            match self.gaps.last_mut() {
+                Some(gap) if gap.logical == self.logical => gap.after = self.physical,
+                _ => self.gaps.push(Gap {
+                    logical: self.logical,
+                    before,
+                    after: self.physical,
+                }),
+            }
+        }
+
+        fn gap(&self, logical: usize) -> Option<&Gap> {
+            let count = self.gaps.partition_point(|gap| gap.logical <= logical);
+            count.checked_sub(1).map(|k| &self.gaps[k])
+        }
+
+        // 逻辑偏移处的字符在物理输入中的起始偏移
+        fn physical_start(&self, logical: usize) -> usize {
+            match self.gap(logical) {
+                Some(gap) => gap.after + (logical - gap.logical),
+                None => logical,
+            }
+        }
+
+        // 逻辑偏移之前的字符在物理输入中的结束偏移
+        fn physical_end(&self, logical: usize) -> usize {
+            match self.gap(logical) {
+                Some(gap) if gap.logical == logical => gap.before,
+                Some(gap) => gap.after + (logical - gap.logical),
+                None => logical,
+            }
+        }
     }
 
-    #[derive(Debug, Clone)]
-    pub struct TokenLocation {
-        row: usize,
-        col: usize,
-    }
+    // 输入来源：整段借用的字符串，或按块读取的 BufRead。
+    // 整段输入不保存翻译结果，逻辑偏移处的字符通过 Translation 的 gaps 在原始输入中找到
+    enum Source<'src> {
+        Str(&'src str),
+        Reader {
+            reader: Box<dyn BufRead + 'src>,
+            buffer: Vec<u8>, // 尚未丢弃的逻辑输入，buffer[0] 位于逻辑偏移 base 处
+            base: usize,
+            raw: Vec<u8>, // 已读入但尚未翻译的物理输入
+            eof: bool,
+        },
+    }
+
+    // 流式读取时，已消费的输入超过该长度才丢弃，避免频繁搬移缓冲区
+    const DISCARD_THRESHOLD: usize = 64 * 1024;
+
+    pub struct Lexer<'src> {
+        state: State,
+        start_index: usize,
+        index: usize,
+        retain_from: usize, // 流式读取时仍需保留的输入起点：未完成的 token 的开头，或注释中的当前位置
+        file_id: usize,
+        source: Source<'src>,                                     // 输入来源
+        line_starts: Vec<usize>,                                  // 每一行起始处的字节偏移
+        pending: VecDeque<Result<(Token<'src>, Span), LexError>>, // 已识别但尚未取走的 token 与错误
+        after_directive: bool,                                    // 上一个 token 是否为指令开头的 #
+        finished: bool,                                           // 是否已经输出 EOF
+        encoding: Encoding,              // 正在识别的字符常量或字符串的编码前缀
+        standard: LanguageStandard,      // 输入遵循的语言标准
+        language: Language,              // 输入是 C 还是 C++
+        line_start: bool,                // 当前行是否还没有输出 token
+        in_directive: bool,              // 是否位于预处理指令行中
+        expect_header: bool,             // 下一个 token 是否可以是头文件名
+        io_error: Option<io::Error>,     // 读取输入时遇到的错误
+        translation: Translation,        // 逻辑偏移与物理偏移的对应关系
+        case_insensitive_keywords: bool, // 关键字是否忽略大小写
+    }
+    impl<'src> Lexer<'src> {
+        pub fn new(input: &'src str) -> Self {
+            let mut line_starts: Vec<usize> = vec![0];
+            for (offset, byte) in input.bytes().enumerate() {
+                if byte == b'\n' {
+                    line_starts.push(offset + 1);
+                }
+            }
+            let mut lexer = Lexer::with_source(Source::Str(input), line_starts);
+            lexer.translate_source();
+            lexer
+        }
+
+        // 从任意 BufRead 按块读取输入，token 不再借用输入，内存占用与单个 token 长度相关
+        pub fn from_reader<R: BufRead + 'src>(reader: R) -> Self {
+            let source = Source::Reader {
+                reader: Box::new(reader),
+                buffer: Vec::new(),
+                base: 0,
+                raw: Vec::new(),
+                eof: false,
+            };
+            Lexer::with_source(source, vec![0])
+        }
+
+        fn with_source(source: Source<'src>, line_starts: Vec<usize>) -> Self {
+            Lexer {
+                state: State::Start,
+                start_index: 0,
+                index: 0,
+                retain_from: 0,
+                file_id: 0,
+                source,
+                line_starts,              // 行首字节偏移，用于推导行列
+                pending: VecDeque::new(), // 待输出的 token 与错误
+                after_directive: false,
+                finished: false,
+                encoding: Encoding::Plain,
+                standard: LanguageStandard::default(),
+                language: Language::default(),
+                line_start: true,
+                in_directive: false,
+                expect_header: false,
+                io_error: None,
+                translation: Translation::default(),
+                case_insensitive_keywords: false,
+            }
+        }
+
+        // 设置输出 Span 中的文件编号，便于区分多个输入文件
+        pub fn with_file_id(mut self, file_id: usize) -> Self {
+            self.file_id = file_id;
+            self
+        }
+
+        // 设置输入遵循的语言标准，默认为 C17
+        pub fn with_standard(mut self, standard: LanguageStandard) -> Self {
+            self.standard = standard;
+            self
+        }
+
+        // 设置输入的语言，默认为 C；C++ 下按 C++ 识别关键字、运算符、原始字符串与用户定义字面量
+        pub fn with_language(mut self, language: Language) -> Self {
+            self.language = language;
+            self
+        }
+
+        // 关键字默认区分大小写；开启后 While、INT 等也识别为关键字，仅供需要这种写法的教学方言使用
+        pub fn with_case_insensitive_keywords(mut self, enabled: bool) -> Self {
+            self.case_insensitive_keywords = enabled;
+            self
+        }
+
+        // 开启后先把 ??= 等三字符组替换为对应的字符，Span 仍指向原始的三个字符
+        pub fn with_trigraphs(mut self, enabled: bool) -> Self {
+            self.translation.trigraphs = enabled;
+            self.translate_source();
+            self
+        }
+
+        // 整段输入需要翻译时预先记录逻辑与物理偏移的对应关系，不拷贝输入
+        fn translate_source(&mut self) {
+            let Source::Str(input) = self.source else {
+                return;
+            };
+            self.translation = Translation::new(self.translation.trigraphs);
+            if self.translation.needed(input) {
+                self.translation
+                    .translate(input.as_bytes(), &mut Discard, true);
+            }
+        }
+
+        // 读取输入时遇到的错误，迭代器在此之后视为输入结束
+        pub fn take_io_error(&mut self) -> Option<io::Error> {
+            self.io_error.take()
+        }
+
+        // 流式读取时缓冲区中尚未丢弃的输入长度，整段输入时为 0
+        pub fn buffered_len(&self) -> usize {
+            match &self.source {
+                Source::Reader { buffer, raw, .. } => buffer.len() + raw.len(),
+                _ => 0,
+            }
+        }
+
+        // 由字节偏移推导行列位置
+        pub fn location(&self, span: Span) -> TokenLocation {
+            let row = match self.line_starts.binary_search(&span.start) {
+                Ok(line) => line,
+                Err(line) => line - 1,
+            };
+            TokenLocation {
+                row: row + 1,
+                col: span.start - self.line_starts[row] + 1,
+                span,
+            }
+        }
+
+        // 从 reader 再读入一块数据，输入已结束时返回 false
+        fn fill(&mut self) -> bool {
+            let Source::Reader {
+                reader,
+                buffer,
+                base,
+                raw,
+                eof,
+            } = &mut self.source
+            else {
+                return false;
+            };
+            if *eof {
+                return false;
+            }
+            // 每次读入新数据前丢弃不再需要的输入，长注释不会使缓冲区无限增长
+            if self.retain_from - *base >= DISCARD_THRESHOLD {
+                buffer.drain(..self.retain_from - *base);
+                *base = self.retain_from;
+            }
+            let chunk = match reader.fill_buf() {
+                Ok(chunk) => chunk,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => return true,
+                Err(error) => {
+                    self.io_error = Some(error);
+                    *eof = true;
+                    return false;
+                }
+            };
+            if chunk.is_empty() {
+                // 输入结束，翻译之前留下的不完整序列
+                *eof = true;
+                let len = buffer.len();
+                self.translation.translate(raw, buffer, true);
+                raw.clear();
+                return buffer.len() > len;
+            }
+            let offset = self.translation.physical + raw.len();
+            for (i, &byte) in chunk.iter().enumerate() {
+                if byte == b'\n' {
+                    self.line_starts.push(offset + i + 1);
+                }
+            }
+            raw.extend_from_slice(chunk);
+            let len = chunk.len();
+            reader.consume(len);
+            let used = self.translation.translate(raw, buffer, false);
+            raw.drain(..used);
+            true
+        }
+
+        // 当前已读入输入的末尾偏移，输入读完后即为输入总长度
+        fn end_offset(&self) -> usize {
+            match &self.source {
+                // 翻译删除的续行与三字符组多出的字节不计入逻辑输入
+                Source::Str(src) => {
+                    src.len() + self.translation.logical - self.translation.physical
+                }
+                Source::Reader { buffer, base, .. } => *base + buffer.len(),
+            }
+        }
+
+        // index 处是否还有输入字节，必要时从 reader 继续读取
+        fn has_byte(&mut self, index: usize) -> bool {
+            while index >= self.end_offset() {
+                if !self.fill() {
+                    return false;
+                }
+            }
+            true
+        }
+
+        // index 是否尚未越过末尾的哨兵空格
+        fn before_end(&mut self, index: usize) -> bool {
+            self.has_byte(index) || index == self.end_offset()
+        }
+
+        // 读取 index 处的字节，输入末尾视为一个哨兵空格
+        fn peek(&mut self, index: usize) -> u8 {
+            if !self.has_byte(index) {
+                return b' ';
+            }
+            match &self.source {
+                Source::Str(src) if self.translation.gaps.is_empty() => src.as_bytes()[index],
+                Source::Str(src) => {
+                    // 逻辑字符从物理偏移处开始，可能是一个三字符组
+                    let bytes = &src.as_bytes()[self.translation.physical_start(index)..];
+                    match bytes {
+                        [b'?', b'?', c, ..] if self.translation.trigraphs => {
+                            trigraph(*c).unwrap_or(b'?')
+                        }
+                        _ => bytes[0],
+                    }
+                }
+                Source::Reader { buffer, base, .. } => buffer[index - base],
+            }
+        }
+
+        // 解码 index 处的完整字符，用于报告非 ASCII 字符
+        fn char_at(&mut self, index: usize) -> char {
+            let width = match self.peek(index) {
+                0xf0..=0xff => 4,
+                0xe0..=0xef => 3,
+                0xc0..=0xdf => 2,
+                _ => 1,
+            };
+            self.has_byte(index + width - 1);
+            let end = (index + width).min(self.end_offset());
+            self.slice(index, end).chars().next().unwrap_or(' ')
+        }
+
+        // 取出逻辑输入中 [start, end) 的文本，整段输入且其中没有经过翻译的字符时直接借用，不产生拷贝
+        fn slice(&self, start: usize, end: usize) -> Cow<'src, str> {
+            match &self.source {
+                Source::Str(src) if self.translation.gaps.is_empty() => {
+                    Cow::Borrowed(&src[start..end])
+                }
+                Source::Str(src) => {
+                    // 只有跨越续行或含有三字符组的 token 才翻译其中的一段并拷贝
+                    let physical_start = self.translation.physical_start(start);
+                    let physical_end = self.translation.physical_end(end).max(physical_start);
+                    let text = &src[physical_start..physical_end];
+                    if physical_end - physical_start == end - start {
+                        Cow::Borrowed(text)
+                    } else {
+                        Cow::Owned(spelling(text, self.translation.trigraphs).into_owned())
+                    }
+                }
+                Source::Reader { buffer, base, .. } => Cow::Owned(
+                    String::from_utf8_lossy(&buffer[start - base..end - base]).into_owned(),
+                ),
+            }
+        }
+
+        // 逻辑输入中 [start, end) 对应物理输入中的 Span，哨兵空格不计入输入
+        fn span(&self, start: usize, end: usize) -> Span {
+            let end = end.min(self.end_offset());
+            let physical_start = self.translation.physical_start(start);
+            let physical_end = if end > start {
+                self.translation.physical_end(end)
+            } else {
+                physical_start
+            };
+            Span::new(self.file_id, physical_start, physical_end)
+        }
+
+        // 记录一个从 start_index 开始、到 end（不含）结束的 token
+        fn push_token(&mut self, token: Token<'src>, end: usize) {
+            let span = self.span(self.start_index, end);
+            // #include、#include_next、#import 之后可以是头文件名
+            self.expect_header = self.in_directive
+                && self.after_directive
+                && matches!(&token, Token::Identifiers(name)
+                    if name == "include" || name == "include_next" || name == "import");
+            self.after_directive = token == Token::Directive;
+            if self.after_directive {
+                self.in_directive = true;
+            }
+            self.line_start = false;
+            self.pending.push_back(Ok((token, span)));
+        }
+
+        // 行首的 # 开始一条预处理指令，其他位置是宏中的字符串化运算符
+        fn push_hash(&mut self, end: usize) {
+            let token = if self.line_start && !self.in_directive {
+                Token::Directive
+            } else {
+                Token::Delimiters(Delimiter::Hash)
+            };
+            self.push_token(token, end);
+        }
+
+        fn push_error(&mut self, error: LexError) {
+            self.pending.push_back(Err(error));
+        }
+
+        // 记录格式错误的常量：报告错误，并输出覆盖整个常量的 Error token，使后续处理仍能看到这里有一个操作数
+        fn push_malformed(&mut self, error: LexError, end: usize) {
+            self.push_error(error);
+            self.encoding = Encoding::Plain;
+            self.push_token(Token::Error, end);
+        }
+
+        // 格式错误的数字常量延伸到其后紧跟的字母、数字、下划线与小数点为止，避免 0x 之后的 g 之类
+        // 被当作新的 token，返回常量的结束位置
+        fn push_malformed_number(&mut self, error: LexError, end: usize) -> usize {
+            let mut end = end;
+            while matches!(self.peek(end), b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'.') {
+                end += 1;
+            }
+            self.push_malformed(error, end);
+            end
+        }
+
+        // C23 与 C++ 允许在数字中使用 ' 作为分隔符
+        fn digit_separators(&self) -> bool {
+            self.standard >= LanguageStandard::C23 || self.language == Language::Cpp
+        }
+
+        // C23 的数字分隔符必须位于两个数字之间，位置不对时报错但仍跳过它继续识别
+        fn digit_separator(&mut self, index: usize, is_digit: fn(&u8) -> bool) -> usize {
+            if !is_digit(&self.peek(index - 1)) || !is_digit(&self.peek(index + 1)) {
+                self.push_error(LexError::InvalidDigitSeparator(self.span(index, index + 1)));
+            }
+            index + 1
+        }
+
+        // 数字常量从开头到 end 的文本，去掉其中的数字分隔符
+        fn number_text(&mut self, end: usize) -> Cow<'src, str> {
+            let text = self.slice(self.start_index, end);
+            if text.contains('\'') {
+                return Cow::Owned(text.replace('\'', ""));
+            }
+            text
+        }
+
+        // 整数常量的数字部分结束于 digits_end，继续读取后缀并按 C 标准确定类型，返回常量的结束位置
+        fn push_integer(&mut self, digits_end: usize, radix: Radix) -> usize {
+            let mut end = digits_end;
+            while matches!(self.peek(end), b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_') {
+                end += 1;
+            }
+            let suffix_text = self.slice(digits_end, end);
+            let (suffix, user_suffix) = match IntegerSuffix::parse(&suffix_text) {
+                Some(suffix) => (suffix, None),
+                None if self.language == Language::Cpp => (IntegerSuffix::None, Some(suffix_text)),
+                None => {
+                    let error = LexError::InvalidIntegerSuffix(self.span(digits_end, end));
+                    return self.push_malformed_number(error, end);
+                }
+            };
+            let text = self.number_text(digits_end);
+            let digits = &text[radix.prefix_len()..];
+            let Ok(value) = u64::from_str_radix(digits, radix.base()) else {
+                let error = LexError::IntegerOverflow(self.span(self.start_index, end));
+                return self.push_malformed_number(error, end);
+            };
+            let ty = match IntegerType::of(value, radix, suffix) {
+                Some(ty) => ty,
+                None => {
+                    self.push_error(LexError::IntegerTooLargeForSigned(
+                        self.span(self.start_index, end),
+                    ));
+                    IntegerType::UnsignedLongLong
+                }
+            };
+            let constant = IntegerConstant {
+                value,
+                radix,
+                suffix,
+                ty,
+            };
+            self.push_literal(Token::Numbers(Number::Integer(constant)), user_suffix, end);
+            end
+        }
+
+        // 浮点常量的数字部分结束于 digits_end，继续读取后缀，返回常量的结束位置
+        fn push_float(&mut self, digits_end: usize) -> usize {
+            let mut end = digits_end;
+            while matches!(self.peek(end), b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_') {
+                end += 1;
+            }
+            let suffix_text = self.slice(digits_end, end);
+            let (ty, user_suffix) = match FloatType::from_suffix(&suffix_text) {
+                Some(ty) => (ty, None),
+                None if self.language == Language::Cpp => (FloatType::Double, Some(suffix_text)),
+                None => {
+                    let error = LexError::InvalidFloatSuffix(self.span(digits_end, end));
+                    return self.push_malformed_number(error, end);
+                }
+            };
+            let text = self.number_text(digits_end);
+            let (radix, mut value) =
+                match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+                    Some(hex) => match parse_hex_float(hex) {
+                        Some(value) => (Radix::Hexadecimal, value),
+                        None => {
+                            let error =
+                                LexError::MissingHexDigits(self.span(self.start_index, end));
+                            return self.push_malformed_number(error, end);
+                        }
+                    },
+                    None => match text.parse::<f64>() {
+                        Ok(value) => (Radix::Decimal, value),
+                        Err(_) => {
+                            let error = LexError::MalformedFloat(self.span(self.start_index, end));
+                            return self.push_malformed_number(error, end);
+                        }
+                    },
+                };
+            if ty == FloatType::Float {
+                value = value as f32 as f64;
+            }
+            let constant = FloatConstant { value, radix, ty };
+            self.push_literal(Token::Numbers(Number::Float(constant)), user_suffix, end);
+            end
+        }
+
+        // 记录一个常量，C++ 中带有用户定义后缀时包装为 UserDefinedLiteral
+        fn push_literal(
+            &mut self,
+            literal: Token<'src>,
+            suffix: Option<Cow<'src, str>>,
+            end: usize,
+        ) {
+            let token = match suffix {
+                Some(suffix) => Token::UserDefinedLiteral(UserDefinedLiteral {
+                    literal: Box::new(literal),
+                    suffix,
+                }),
+                None => literal,
+            };
+            self.push_token(token, end);
+        }
+
+        // C++ 中紧跟在字符或字符串常量后的标识符是用户定义后缀，返回后缀的结束位置，没有后缀时返回 start
+        fn user_suffix_end(&mut self, start: usize) -> usize {
+            if self.language != Language::Cpp
+                || !matches!(self.peek(start), b'a'..=b'z' | b'A'..=b'Z' | b'_')
+            {
+                return start;
+            }
+            let mut end = start + 1;
+            while matches!(self.peek(end), b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_') {
+                end += 1;
+            }
+            end
+        }
+
+        // 头文件名 <...> 或 "..." 的结束位置，必须在同一行内闭合
+        fn header_name_end(&mut self, start: usize) -> Option<usize> {
+            let close = if self.peek(start) == b'<' { b'>' } else { b'"' };
+            let mut index = start + 1;
+            while self.has_byte(index) && self.peek(index) != b'\n' {
+                if self.peek(index) == close {
+                    return Some(index + 1);
+                }
+                index += 1;
+            }
+            None
+        }
+
+        // index 处可以出现在标识符中的通用字符名或非 ASCII 字符的字节长度，不是时为 0；
+        // 通用字符名在这里只检查格式，它代表的字符是否合法由 push_identifier 检查
+        fn extended_char_len(&mut self, index: usize) -> usize {
+            match self.peek(index) {
+                b'\\' => {
+                    let count = match self.peek(index + 1) {
+                        b'u' => 4,
+                        b'U' => 8,
+                        _ => return 0,
+                    };
+                    let digits = index + 2..index + 2 + count;
+                    if digits.into_iter().all(|i| self.peek(i).is_ascii_hexdigit()) {
+                        count + 2
+                    } else {
+                        0
+                    }
+                }
+                0x80..=0xff => {
+                    let ch = self.char_at(index);
+                    if is_xid_continue(ch) {
+                        ch.len_utf8()
+                    } else {
+                        0
+                    }
+                }
+                _ => 0,
+            }
+        }
+
+        // 标识符从 start_index 开始、到 end 结束，按语言标准识别关键字；含有通用字符名或非 ASCII 字符时
+        // 先解码再规范化为 NFC，以便比较，首字符必须是 XID_Start，其余必须是 XID_Continue
+        fn push_identifier(&mut self, end: usize) {
+            let text = self.slice(self.start_index, end);
+            if text.is_ascii() && !text.contains('\\') {
+                // 关键字只含 ASCII 字母与下划线，其余直接借用输入切片
+                let alternative = ALTERNATIVE_TOKENS
+                    .iter()
+                    .find(|(spelling, _)| self.language == Language::Cpp && *spelling == text);
+                let token = match (alternative, self.get_keyword(&text)) {
+                    (Some((_, operator)), _) => Token::Operators(operator.clone()),
+                    (None, Some(keyword)) => Token::Keywords(keyword),
+                    (None, None) => Token::Identifiers(text),
+                };
+                self.push_token(token, end);
+                return;
+            }
+            let mut name = String::with_capacity(text.len());
+            let mut chars = text.char_indices();
+            while let Some((offset, c)) = chars.next() {
+                let mut len = c.len_utf8();
+                let mut ch = Some(c);
+                if c == '\\' {
+                    // 格式已由 extended_char_len 检查，\u 后4位、\U 后8位16进制数字
+                    let count = if text[offset + 1..].starts_with('u') {
+                        4
+                    } else {
+                        8
+                    };
+                    len = count + 2;
+                    let digits = &text[offset + 2..offset + len];
+                    ch = u32::from_str_radix(digits, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .filter(|&ch| ch >= '\u{a0}' || matches!(ch, '$' | '@' | '`'));
+                    for _ in 1..len {
+                        chars.next();
+                    }
+                }
+                let span = self.span(self.start_index + offset, self.start_index + offset + len);
+                let Some(ch) = ch else {
+                    self.push_error(LexError::InvalidUniversalCharacter(span));
+                    continue;
+                };
+                let allowed = if offset == 0 {
+                    is_xid_start(ch) || ch == '_'
+                } else {
+                    is_xid_continue(ch)
+                };
+                if !allowed {
+                    self.push_error(LexError::InvalidIdentifierCharacter { ch, span });
+                }
+                name.push(ch);
+            }
+            // 没有通用字符名且已经是 NFC 形式时仍借用输入
+            let name = if !text.contains('\\') && is_nfc(&name) {
+                text
+            } else {
+                Cow::Owned(name.nfc().collect())
+            };
+            self.push_token(Token::Identifiers(name), end);
+        }
+
+        // 标识符 L、u、U、u8 后紧跟引号时，作为字符常量或字符串的编码前缀
+        fn enter_prefixed_literal(&mut self, quote_index: usize) -> bool {
+            let prefix = self.slice(self.start_index, quote_index);
+            let Some(encoding) = Encoding::from_prefix(&prefix) else {
+                return false;
+            };
+            self.encoding = encoding;
+            self.state = if self.peek(quote_index) == b'\'' {
+                State::State41
+            } else {
+                State::State42
+            };
+            true
+        }
 
-    #[derive(Debug, Clone, Copy, PartialEq)]
-    enum State {
-        Start,  //开始匹配
-        State1, //开始->字母或数字或下划线 State1->字母或数字或下划线
-        State11,
-        State12,
-        State2, //开始->数字
-        State20,
-        State21,
-        State22,
-        State23,
-        State24,
-        State25,
-        State26,
-        State261,
-        State27,  //匹配二进制
-        State28,  //匹配8进制
-        State281, //8进制异常处理
-        // State29, // 非多个e或是小数点的错误处理
-        State3,  //开始->运算符
-        State31, // State31可匹配<<=
-        State32, // State32可匹配>>=
-        State33, //处理注释/* */
-        State4,  //开始->界符
-        State41, //匹配单引号
-        State42, //匹配双引号
-    }
+        // C++ 中标识符 R、LR、uR、UR、u8R 后紧跟双引号时开始原始字符串
+        fn is_raw_string_prefix(&self, quote_index: usize) -> bool {
+            self.language == Language::Cpp
+                && matches!(
+                    &*self.slice(self.start_index, quote_index),
+                    "R" | "LR" | "uR" | "UR" | "u8R"
+                )
+        }
 
-    pub struct Lexer {
-        state: State,
-        start_index: usize,
-        index: usize,
-        row: usize,
-        col: usize,
-        chars: Vec<char>,
-        chars_len: usize,
-        tokens: Vec<Token>,
-        tokens_location: Vec<TokenLocation>,
-        errors: Vec<String>, // 记录错误信息
-    }
-    impl Lexer {
-        pub fn new(input: &str) -> Self {
-            let mut chars: Vec<char> = input.chars().collect();
-            chars.push(' ');
-            let chars_len: usize = chars.len();
-            Lexer {
-                state: State::Start,
-                start_index: 0,
-                index: 0,
-                row: 1,
-                col: 1,
-                chars,                       // 输入字符序列
-                chars_len,                   // 输入字符序列长度
-                tokens_location: Vec::new(), // 输出token所在位置
-                tokens: Vec::new(),          // 输出token
-                errors: Vec::new(),          // 初始化错误向量
+        // C++ 原始字符串 R"delim(...)delim"，可以跨行，内容不处理转义；整段输入时内容与拼写都取自原始输入，
+        // 其中的续行与三字符组保持原样。分隔符最多16个字符，不能含空白、括号与反斜杠，返回结束位置
+        fn push_raw_string(&mut self, quote_index: usize) -> usize {
+            let prefix = self.slice(self.start_index, quote_index);
+            let encoding =
+                Encoding::from_prefix(&prefix[..prefix.len() - 1]).unwrap_or(Encoding::Plain);
+            let mut open = quote_index + 1;
+            while self.has_byte(open) && self.peek(open) != b'(' {
+                let invalid = matches!(
+                    self.peek(open),
+                    b' ' | b'\t' | b'\r' | b'\n' | b'\x0b' | b'\x0c' | b')' | b'\\' | b'"'
+                );
+                if invalid || open - quote_index > 16 {
+                    // 跳过本行剩余部分，换行交给 Start 处理
+                    let mut end = open;
+                    while self.has_byte(end) && self.peek(end) != b'\n' {
+                        end += 1;
+                    }
+                    let error =
+                        LexError::InvalidRawStringDelimiter(self.span(self.start_index, open + 1));
+                    self.push_malformed(error, end);
+                    return end;
+                }
+                open += 1;
+            }
+            let delimiter = self.slice(quote_index + 1, open).into_owned();
+            let mut close = open + 1;
+            loop {
+                if !self.has_byte(close) {
+                    // 原始字符串可以跨行，未闭合时一直延伸到输入末尾
+                    let error = LexError::UnterminatedString(self.span(self.start_index, close));
+                    self.push_malformed(error, close);
+                    return close;
+                }
+                if self.peek(close) == b')' && self.closes_raw_string(close + 1, &delimiter) {
+                    break;
+                }
+                close += 1;
+            }
+            let end = close + delimiter.len() + 2;
+            let body = match &self.source {
+                Source::Str(source) => Cow::Borrowed(
+                    &source[self.translation.physical_end(open + 1)
+                        ..self.translation.physical_start(close)],
+                ),
+                _ => self.slice(open + 1, close),
+            };
+            let mut value = Vec::new();
+            for ch in body.chars() {
+                encoding.encode(&mut value, ch);
+            }
+            let raw = match &self.source {
+                Source::Str(source) => Cow::Borrowed(
+                    &source[self.translation.physical_start(self.start_index)
+                        ..self.translation.physical_end(end)],
+                ),
+                _ => self.slice(self.start_index, end),
+            };
+            let suffix_end = self.user_suffix_end(end);
+            let suffix = (suffix_end > end).then(|| self.slice(end, suffix_end));
+            self.push_literal(
+                Token::Strings(StringLiteral {
+                    encoding,
+                    raw,
+                    value,
+                }),
+                suffix,
+                suffix_end,
+            );
+            suffix_end
+        }
+
+        // index 处是否为原始字符串的分隔符加上结束的双引号
+        fn closes_raw_string(&mut self, index: usize, delimiter: &str) -> bool {
+            for (offset, byte) in delimiter.bytes().enumerate() {
+                if self.peek(index + offset) != byte {
+                    return false;
+                }
+            }
+            self.peek(index + delimiter.len()) == b'"'
+        }
+
+        // 字符常量的结束引号位于 end 之前，解码其内容并按 C 规则计算值，返回包括用户定义后缀在内的结束位置
+        fn push_char_constant(&mut self, end: usize) -> usize {
+            let raw = self.slice(self.start_index, end);
+            let encoding = self.encoding;
+            let body_start = encoding.prefix().len() + 1;
+            let units = self.decode_literal(
+                &raw[body_start..raw.len() - 1],
+                self.start_index + body_start,
+                encoding,
+            );
+            let span = self.span(self.start_index, end);
+            if raw.len() == body_start + 1 {
+                self.push_malformed(LexError::EmptyCharConstant(span), end);
+                return end;
+            }
+            let value = match (encoding, units.len()) {
+                (_, 0) => 0, // 内容全部是非法转义，错误已经报告
+                (Encoding::Plain, _) if units.len() > 1 => {
+                    // 与 GCC 一致：逐个字节左移拼接，截断为 32 位 int
+                    self.push_error(LexError::MultiCharConstant(span));
+                    units
+                        .iter()
+                        .fold(0u32, |acc, unit| acc << 8 | (unit & 0xff)) as i32
+                        as i64
+                }
+                (_, count) => {
+                    // 带前缀的字符常量只保留最后一个编码单元
+                    if count > 1 {
+                        self.push_error(LexError::MultiCharConstant(span));
+                    }
+                    let unit = units[count - 1];
+                    match encoding {
+                        Encoding::Plain => unit as u8 as i8 as i64, // char 按有符号处理
+                        Encoding::Wide => unit as i32 as i64,       // wchar_t 为 32 位 int
+                        Encoding::Utf8 | Encoding::Utf16 | Encoding::Utf32 => unit as i64,
+                    }
+                }
+            };
+            self.encoding = Encoding::Plain;
+            let suffix_end = self.user_suffix_end(end);
+            let suffix = (suffix_end > end).then(|| self.slice(end, suffix_end));
+            self.push_literal(
+                Token::CharConstant(CharLiteral {
+                    encoding,
+                    raw,
+                    value,
+                }),
+                suffix,
+                suffix_end,
+            );
+            suffix_end
+        }
+        // 解码引号内的文本，返回 encoding 对应的编码单元，offset 为 body 在输入中的偏移
+        fn decode_literal(&mut self, body: &str, offset: usize, encoding: Encoding) -> Vec<u32> {
+            let bytes = body.as_bytes();
+            let mut units: Vec<u32> = Vec::new();
+            let mut i = 0;
+            while i < bytes.len() {
+                if bytes[i] != b'\\' {
+                    let ch = body[i..].chars().next().unwrap_or(' ');
+                    encoding.encode(&mut units, ch);
+                    i += ch.len_utf8();
+                    continue;
+                }
+                let start = i;
+                i += 1;
+                if i >= bytes.len() {
+                    break;
+                }
+                let simple = match bytes[i] {
+                    b'\'' | b'"' | b'?' | b'\\' => Some(bytes[i] as u32),
+                    b'a' => Some(0x07),
+                    b'b' => Some(0x08),
+                    b'f' => Some(0x0c),
+                    b'n' => Some(0x0a),
+                    b'r' => Some(0x0d),
+                    b't' => Some(0x09),
+                    b'v' => Some(0x0b),
+                    _ => None,
+                };
+                if let Some(unit) = simple {
+                    units.push(unit);
+                    i += 1;
+                    continue;
+                }
+                match bytes[i] {
+                    b'0'..=b'7' => {
+                        // 8进制转义最多3位
+                        let mut value: u64 = 0;
+                        let digits_end = (i + 3).min(bytes.len());
+                        while i < digits_end && (b'0'..=b'7').contains(&bytes[i]) {
+                            value = value * 8 + (bytes[i] - b'0') as u64;
+                            i += 1;
+                        }
+                        if value > encoding.max_unit() {
+                            self.push_error(LexError::EscapeOutOfRange(
+                                self.span(offset + start, offset + i),
+                            ));
+                        }
+                        units.push((value & encoding.max_unit()) as u32);
+                    }
+                    b'x' => {
+                        // 16进制转义不限位数，直到遇到非16进制字符
+                        i += 1;
+                        let digits_start = i;
+                        let mut value: u64 = 0;
+                        while i < bytes.len() && bytes[i].is_ascii_hexdigit() {
+                            let digit = (bytes[i] as char).to_digit(16).unwrap_or(0) as u64;
+                            value = value.saturating_mul(16).saturating_add(digit);
+                            i += 1;
+                        }
+                        let span = self.span(offset + start, offset + i);
+                        if i == digits_start {
+                            self.push_error(LexError::MissingHexEscapeDigits(span));
+                            continue;
+                        }
+                        if value > encoding.max_unit() {
+                            self.push_error(LexError::EscapeOutOfRange(span));
+                        }
+                        units.push((value & encoding.max_unit()) as u32);
+                    }
+                    b'u' | b'U' => {
+                        // 通用字符名必须恰好4位或8位16进制数字
+                        let count = if bytes[i] == b'u' { 4 } else { 8 };
+                        i += 1;
+                        let digits_end = (i + count).min(bytes.len());
+                        let digits = &body[i..digits_end];
+                        i = digits_end;
+                        let ch = if digits.len() == count
+                            && digits.bytes().all(|b| b.is_ascii_hexdigit())
+                        {
+                            u32::from_str_radix(digits, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                        } else {
+                            None
+                        };
+                        match ch {
+                            Some(ch) => encoding.encode(&mut units, ch),
+                            None => self.push_error(LexError::InvalidUniversalCharacter(
+                                self.span(offset + start, offset + i),
+                            )),
+                        }
+                    }
+                    _ => {
+                        // 未知转义按字符本身处理，并给出警告
+                        let ch = body[i..].chars().next().unwrap_or(' ');
+                        i += ch.len_utf8();
+                        self.push_error(LexError::UnknownEscape {
+                            ch,
+                            span: self.span(offset + start, offset + i),
+                        });
+                        encoding.encode(&mut units, ch);
+                    }
+                }
             }
+            units
         }
 
         // 获取关键字的函数
         fn get_keyword(&mut self, keyword_str: &str) -> Option<Keyword> {
-            match keyword_str.to_lowercase().as_str() {
-                "auto" => Some(Keyword::Auto),
-                "break" => Some(Keyword::Break),
-                "case" => Some(Keyword::Case),
-                "char" => Some(Keyword::Char),
-                "const" => Some(Keyword::Const),
-                "continue" => Some(Keyword::Continue),
-                "default" => Some(Keyword::Default),
-                "do" => Some(Keyword::Do),
-                "double" => Some(Keyword::Double),
-                "else" => Some(Keyword::Else),
-                "enum" => Some(Keyword::Enum),
-                "extern" => Some(Keyword::Extern),
-                "float" => Some(Keyword::Float),
-                "for" => Some(Keyword::For),
-                "goto" => Some(Keyword::Goto),
-                "if" => Some(Keyword::If),
-                "int" => Some(Keyword::Int),
-                "long" => Some(Keyword::Long),
-                "register" => Some(Keyword::Register),
-                "return" => Some(Keyword::Return),
-                "short" => Some(Keyword::Short),
-                "signed" => Some(Keyword::Signed),
-                "sizeof" => Some(Keyword::Sizeof),
-                "static" => Some(Keyword::Static),
-                "struct" => Some(Keyword::Struct),
-                "switch" => Some(Keyword::Switch),
-                "typedef" => Some(Keyword::Typedef),
-                "union" => Some(Keyword::Union),
-                "unsigned" => Some(Keyword::Unsigned),
-                "void" => Some(Keyword::Void),
-                "volatile" => Some(Keyword::Volatile),
-                "while" => Some(Keyword::While),
-                _ => None,
+            if self.case_insensitive_keywords {
+                return Keyword::lookup_ignore_case(keyword_str, self.language, self.standard);
             }
+            Keyword::lookup(keyword_str, self.language, self.standard)
         }
 
-        pub fn lex(&mut self) -> io::Result<(Vec<Token>, Vec<TokenLocation>, Vec<String>)> {
-            // let mut next_index = 0;
-            while self.index < self.chars_len {
+        // 按需识别下一个 token，输入结束时先返回 EOF，之后返回 None
+        pub fn next_token(&mut self) -> Option<Result<(Token<'src>, Span), LexError>> {
+            while self.pending.is_empty() && self.before_end(self.index) {
+                // 注释的内容不再需要，其余状态要保留正在识别的 token 的全部输入
+                self.retain_from = match self.state {
+                    State::Start | State::State33 => self.index,
+                    _ => self.start_index,
+                };
                 self.index = self.process_char(self.index);
             }
-            self.tokens.push(Token::EOF);
-            self.tokens_location.push(TokenLocation {row: self.row, col: self.col});
-            Ok((
-                self.tokens.clone(),
-                self.tokens_location.clone(),
-                self.errors.clone(),
-            )) // 返回 tokens 和错误信息的元组
+            if let Some(item) = self.pending.pop_front() {
+                return Some(item);
+            }
+            if self.finished {
+                return None;
+            }
+            let end = self.end_offset();
+            if self.in_directive {
+                // 文件最后一行是预处理指令且没有换行
+                self.in_directive = false;
+                return Some(Ok((Token::EndOfDirective, self.span(end, end))));
+            }
+            self.finished = true;
+            let span = self.span(end, end); // EOF 位于哨兵空格处，长度为 0
+            Some(Ok((Token::EOF, span)))
+        }
+
+        pub fn lex(&mut self) -> io::Result<(Vec<Token<'src>>, Vec<TokenLocation>, Vec<LexError>)> {
+            let mut tokens = Vec::new();
+            let mut tokens_location = Vec::new();
+            let mut errors = Vec::new();
+            while let Some(item) = self.next_token() {
+                match item {
+                    Ok((token, span)) => {
+                        tokens.push(token);
+                        tokens_location.push(self.location(span));
+                    }
+                    Err(error) => errors.push(error),
+                }
+            }
+            if let Some(error) = self.io_error.take() {
+                return Err(error);
+            }
+            Ok((tokens, tokens_location, errors)) // 返回 tokens 和错误信息的元组
         }
 
         fn process_char(&mut self, ptr_index: usize) -> usize {
             // 在方法中使用 index 参数来获取当前字符的序号
-            // let c = self.chars[index]; // 通过索引获取字符
+            // let c = self.peek(index); // 通过索引获取字符
             let mut next_index: usize = ptr_index + 1;
             match self.state {
-                State::Start => match self.chars[ptr_index] {
-                    'a'..='z' | 'A'..='Z' | '_' => {
+                State::Start => match self.peek(ptr_index) {
+                    b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
                         self.start_index = ptr_index;
                         self.state = State::State1; // 匹配到字母或下划线-进入State1
-                        return ptr_index;
+                        ptr_index
                     }
-                    '0'..='9' => {
+                    b'0'..=b'9' => {
                         self.start_index = ptr_index;
                         self.state = State::State2; // 匹配到数字-进入State2
-                        return ptr_index;
+                        ptr_index
+                    }
+                    b'\\' | 0x80..=0xff if self.extended_char_len(ptr_index) > 0 => {
+                        // 以通用字符名或非 ASCII 字符开头的标识符
+                        self.start_index = ptr_index;
+                        self.state = State::State11;
+                        ptr_index + self.extended_char_len(ptr_index)
+                    }
+                    b'.' if self.peek(next_index).is_ascii_digit() => {
+                        // .5 这样以小数点开头的浮点数
+                        self.start_index = ptr_index;
+                        self.state = State::State21;
+                        next_index
+                    }
+                    b'<' | b'"'
+                        if self.expect_header && self.header_name_end(ptr_index).is_some() =>
+                    {
+                        next_index = self.header_name_end(ptr_index).unwrap_or(next_index);
+                        self.start_index = ptr_index;
+                        let name = self.slice(ptr_index, next_index);
+                        self.push_token(Token::HeaderName(name), next_index);
+                        next_index
+                    }
+                    b'#' => {
+                        self.start_index = ptr_index;
+                        if self.peek(next_index) == b'#' {
+                            self.push_token(Token::Delimiters(Delimiter::HashHash), next_index + 1);
+                            return next_index + 1;
+                        }
+                        self.push_hash(next_index);
+                        next_index
                     }
-                    '+' | '-' | '*' | '/' | '%' | '=' | '!' | '<' | '>' | '&' | '|' | '^' | '~'
-                    | '.' => {
+                    b'+' | b'-' | b'*' | b'/' | b'%' | b'=' | b'!' | b'<' | b'>' | b'&' | b'|'
+                    | b'^' | b'~' | b'.' => {
                         self.start_index = ptr_index;
                         self.state = State::State3; // 匹配到运算符-进入State3
-                        self.col += 1;
-                        return next_index;
+                        next_index
                     }
-                    ';' | ',' | '(' | ')' | '[' | ']' | '{' | '}' | '\'' | '"' | ':' | '?' => {
+                    b';' | b',' | b'(' | b')' | b'[' | b']' | b'{' | b'}' | b'\'' | b'"' | b':'
+                    | b'?' => {
                         self.start_index = ptr_index;
                         self.state = State::State4; // 匹配到界符-进入State4
-                        self.col += 1;
-                        return next_index;
+                        next_index
                     }
-                    ' ' => {
-                        //匹配到空格-跳过
+                    b' ' | b'\t' | b'\x0b' | b'\x0c' => {
+                        //匹配到空白-跳过
                         self.state = State::Start;
-                        self.col += 1;
-                        return next_index;
+                        next_index
                     }
-                    '\n' => {
-                        //匹配到回车-跳过
+                    b'\r' if self.peek(next_index) != b'\n' => {
+                        // 单独的 \r 也按空白处理
                         self.state = State::Start;
-                        self.row += 1;
-                        self.col = 1;
-                        return next_index;
+                        next_index
+                    }
+                    b'\n' | b'\r' => {
+                        //匹配到换行-跳过，\r\n 算作一个换行，预处理指令在换行处结束
+                        if self.peek(ptr_index) == b'\r' {
+                            next_index += 1;
+                        }
+                        if self.in_directive {
+                            self.start_index = ptr_index;
+                            self.push_token(Token::EndOfDirective, next_index);
+                            self.in_directive = false;
+                        }
+                        self.line_start = true;
+                        self.state = State::Start;
+                        next_index
                     }
                     _ => {
-                        self.errors.push(format!(
-                            "Unknown key: {} in {} index(State::Start)",
-                            &self.chars[ptr_index], ptr_index
-                        ));
-                        self.col += 1;
-                        return next_index;
+                        let ch = self.char_at(ptr_index);
+                        next_index = ptr_index + ch.len_utf8(); // 跳过完整的多字节字符
+                        self.push_error(LexError::UnknownCharacter {
+                            ch,
+                            span: self.span(ptr_index, next_index),
+                        });
+                        next_index
                     }
                 },
-                State::State1 => match self.chars[ptr_index] {
-                    'a'..='z' => {
+                State::State1 => match self.peek(ptr_index) {
+                    b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
                         self.state = State::State11;
-                        self.col += 1;
-                        return next_index;
-                    }
-                    'A'..='Z' | '_' => {
-                        self.state = State::State12;
-                        self.col += 1;
-                        return next_index;
+                        next_index
                     }
                     _ => {
-                        self.errors
-                            .push("Entered a wrong pattern: State1._".to_string());
+                        let ch = self.char_at(ptr_index);
+                        self.push_error(LexError::UnknownCharacter {
+                            ch,
+                            span: self.span(ptr_index, next_index),
+                        });
                         self.state = State::Start;
-                        return ptr_index;
+                        ptr_index
                     }
                 },
-                State::State11 => match self.chars[ptr_index] {
-                    'a'..='z' => {
+                State::State11 => match self.peek(ptr_index) {
+                    b'a'..=b'z' | b'A'..=b'Z' | b'_' | b'0'..=b'9' => {
                         self.state = State::State11;
-                        self.col += 1;
-                        return next_index;
+                        next_index
                     }
-                    'A'..='Z' | '_' | '0'..='9' => {
-                        self.state = State::State12;
-                        self.col += 1;
-                        return next_index;
+                    b'\\' | 0x80..=0xff if self.extended_char_len(ptr_index) > 0 => {
+                        self.state = State::State11;
+                        ptr_index + self.extended_char_len(ptr_index)
                     }
-                    _ => {
-                        let identifier = &self.chars[self.start_index..ptr_index]; // 直接获取字符切片
-                        let identifier_str: String = identifier.iter().collect(); // 将切片转换为字符串
-                        if ptr_index - self.start_index > 10 {
-                            self.tokens.push(Token::Identifiers(identifier_str));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.start_index + self.col - ptr_index,
-                            });
-                        } else {
-                            if let Some(keyword) = self.get_keyword(&identifier_str) {
-                                // 如果 get_keyword 返回 Some，表示找到了关键字
-                                self.tokens.push(Token::Keywords(keyword));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.start_index + self.col - ptr_index,
-                                });
-                            } else {
-                                self.tokens.push(Token::Identifiers(identifier_str));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.start_index + self.col - ptr_index,
-                                });
-                            }
-                        }
-                        self.state = State::Start;
-                        return ptr_index;
+                    // 紧挨着标识符的其他非 ASCII 字符也并入标识符，由 push_identifier 报告为不允许的字符
+                    0x80..=0xff if !self.char_at(ptr_index).is_whitespace() => {
+                        self.state = State::State11;
+                        ptr_index + self.char_at(ptr_index).len_utf8()
                     }
-                },
-                State::State12 => match self.chars[ptr_index] {
-                    'a'..='z' | 'A'..='Z' | '_' | '0'..='9' => {
-                        self.state = State::State12;
-                        self.col += 1;
-                        return next_index;
+                    b'"' if self.is_raw_string_prefix(ptr_index) => {
+                        self.state = State::Start;
+                        self.push_raw_string(ptr_index)
                     }
+                    b'\'' | b'"' if self.enter_prefixed_literal(ptr_index) => next_index,
                     _ => {
-                        let identifier = &self.chars[self.start_index..ptr_index]; // 直接获取字符切片
-                        let identifier_str: String = identifier.iter().collect(); // 将切片转换为字符串
-                        self.tokens.push(Token::Identifiers(identifier_str));
-                        self.tokens_location.push(TokenLocation {
-                            row: self.row,
-                            col: self.start_index + self.col - ptr_index,
-                        });
+                        self.push_identifier(ptr_index);
                         self.state = State::Start;
-                        return ptr_index;
+                        ptr_index
                     }
                 },
-                State::State2 => match self.chars[ptr_index] {
-                    '1'..='9' => {
+                State::State2 => match self.peek(ptr_index) {
+                    b'1'..=b'9' => {
                         self.state = State::State20; // State20开始匹配十进制整数/浮点数
-                        self.col += 1;
-                        return next_index;
+                        next_index
                     }
-                    '0' => {
+                    b'0' => {
                         self.state = State::State25; // State25尝试匹配多进制数
-                        self.col += 1;
-                        return next_index;
+                        next_index
                     }
                     _ => {
-                        self.errors
-                            .push("Entered a wrong pattern: State2._".to_string()); // 不可能金进入该匹配
+                        // 不可能进入该匹配
+                        let ch = self.char_at(ptr_index);
+                        self.push_error(LexError::UnknownCharacter {
+                            ch,
+                            span: self.span(ptr_index, next_index),
+                        });
                         self.state = State::Start;
-                        return ptr_index;
+                        ptr_index
                     }
                 },
                 State::State20 => {
-                    match self.chars[ptr_index] {
-                        '0'..='9' => {
+                    match self.peek(ptr_index) {
+                        b'0'..=b'9' => {
                             self.state = State::State20;
-                            self.col += 1;
-                            return next_index;
+                            next_index
                         }
-                        '.' => {
+                        b'\'' if self.digit_separators() => {
+                            self.digit_separator(ptr_index, u8::is_ascii_digit)
+                        }
+                        b'.' => {
                             self.state = State::State21; // State21不匹配.和e,匹配完第一个字符转为State21
-                            self.col += 1;
-                            return next_index;
+                            next_index
                         }
-                        'e' | 'E' => {
+                        b'e' | b'E' => {
                             self.state = State::State23; // State23不匹配e和.但匹配-或正
-                            self.col += 1;
-                            return next_index;
+                            next_index
                         }
                         _ => {
-                            let mut number: i64 = 0;
-                            for &c in &self.chars[self.start_index..ptr_index] {
-                                if let Some(digit) = c.to_digit(10) {
-                                    number = number * 10 + digit as i64;
-                                }
-                            }
-                            if self.chars[self.start_index] == '-' {
-                                number = 0 - number;
-                            }
-                            self.tokens.push(Token::Numbers(Number::Integer(number)));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.start_index + self.col - ptr_index,
-                            });
                             self.state = State::Start;
-                            return ptr_index;
+                            self.push_integer(ptr_index, Radix::Decimal)
                         }
                     }
                 }
-                State::State21 => match self.chars[ptr_index] {
-                    '0'..='9' => {
-                        self.state = State::State22;
-                        self.col += 1;
-                        return next_index;
-                    }
-                    _ => {
-                        let error_number: String =
-                            self.chars[self.start_index..ptr_index].iter().collect();
-                        self.errors.push(format!("Error number: {}", error_number));
-                        self.state = State::Start;
-                        return ptr_index;
-                    }
-                },
-                State::State22 => {
-                    match self.chars[ptr_index] {
-                        '0'..='9' => {
+                State::State21 | State::State22 => {
+                    // State21 刚读到小数点，State22 已读到小数部分的数字，1. 这样没有小数部分的也合法
+                    match self.peek(ptr_index) {
+                        b'0'..=b'9' => {
+                            self.state = State::State22;
+                            next_index
+                        }
+                        b'\'' if self.digit_separators() => {
                             self.state = State::State22;
-                            self.col += 1;
-                            return next_index;
+                            self.digit_separator(ptr_index, u8::is_ascii_digit)
                         }
-                        'e' | 'E' => {
+                        b'e' | b'E' => {
                             self.state = State::State23; // State23不匹配e和.
-                            self.col += 1;
-                            return next_index;
+                            next_index
                         }
                         _ => {
-                            let number_str: String =
-                                self.chars[self.start_index..ptr_index].iter().collect();
-                            if let Ok(number) = number_str.parse::<f64>() {
-                                self.tokens.push(Token::Numbers(Number::Float(number)));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.start_index + self.col - ptr_index,
-                                });
-                            }
                             self.state = State::Start;
-                            return ptr_index;
+                            self.push_float(ptr_index)
                         }
                     }
                 }
                 State::State23 => {
-                    match self.chars[ptr_index] {
-                        '0'..='9' | '-' | '+' => {
-                            self.state = State::State24; // State23不匹配- +
-                            self.col += 1;
-                            return next_index;
+                    // 十进制的 e 与16进制的 p 之后共用指数部分
+                    match self.peek(ptr_index) {
+                        b'0'..=b'9' => {
+                            self.state = State::State24;
+                            next_index
+                        }
+                        b'-' | b'+' => {
+                            self.state = State::State231; // State231要求符号后跟数字
+                            next_index
                         }
                         _ => {
-                            let error_number: String =
-                                self.chars[self.start_index..ptr_index].iter().collect();
-                            self.errors.push(format!("Error number: {}", error_number));
                             self.state = State::Start;
-                            return ptr_index;
+                            self.push_malformed_number(
+                                LexError::MalformedExponent(self.span(self.start_index, ptr_index)),
+                                ptr_index,
+                            )
                         }
                     }
                 }
+                State::State231 => match self.peek(ptr_index) {
+                    b'0'..=b'9' => {
+                        self.state = State::State24;
+                        next_index
+                    }
+                    _ => {
+                        self.state = State::Start;
+                        self.push_malformed_number(
+                            LexError::MalformedExponent(self.span(self.start_index, ptr_index)),
+                            ptr_index,
+                        )
+                    }
+                },
                 State::State24 => {
-                    match self.chars[ptr_index] {
-                        '0'..='9' => {
+                    match self.peek(ptr_index) {
+                        b'0'..=b'9' => {
                             self.state = State::State24; // State24不匹配-
-                            self.col += 1;
-                            return next_index;
+                            next_index
+                        }
+                        b'\'' if self.digit_separators() => {
+                            self.digit_separator(ptr_index, u8::is_ascii_digit)
                         }
                         _ => {
-                            let number_str: String =
-                                self.chars[self.start_index..ptr_index].iter().collect();
-                            if let Ok(number) = number_str.parse::<f64>() {
-                                self.tokens.push(Token::Numbers(Number::Float(number)));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.start_index + self.col - ptr_index,
-                                });
-                            }
                             self.state = State::Start;
-                            return ptr_index;
+                            self.push_float(ptr_index)
                         }
                     }
                 }
                 State::State25 => {
-                    match self.chars[ptr_index] {
-                        '.' => {
+                    match self.peek(ptr_index) {
+                        b'.' => {
                             self.state = State::State21; // 0.x匹配十进制浮点数 State20不匹配.和e,匹配完第一个字符转为State21
-                            self.col += 1;
-                            return next_index;
+                            next_index
                         }
-                        'x' | 'X' => {
+                        b'x' | b'X' => {
                             self.state = State::State26; // State26匹配16进制数
-                            self.col += 1;
-                            return next_index;
+                            next_index
                         }
-                        'b' | 'B' => {
+                        b'b' | b'B' => {
                             self.state = State::State27; // State27匹配2进制数
-                            self.col += 1;
-                            return next_index;
+                            next_index
                         }
-                        '0'..='7' => {
+                        b'0'..=b'7' => {
                             self.state = State::State28; // State28匹配8进制数
-                            self.col += 1;
-                            return next_index;
+                            next_index
+                        }
+                        b'\'' if self.digit_separators() => {
+                            self.state = State::State28;
+                            self.digit_separator(ptr_index, u8::is_ascii_digit)
                         }
-                        'e' | 'E' => {
+                        b'e' | b'E' => {
                             self.state = State::State23; // 0e State23不匹配e和.但匹配-或正
-                            self.col += 1;
-                            return next_index;
-                        }
-                        '8' | '9' => {
-                            // 不存在的数字
-                            while self.chars[next_index] >= '0' && self.chars[next_index] <= '9' {
-                                next_index += 1;
-                                self.col += 1;
-                            }
-                            let number_str: String =
-                                self.chars[self.start_index..next_index].iter().collect();
-                            self.state = State::Start;
-                            self.errors
-                                .push(format!("Error octal number: {}", number_str));
-                            // self.col += 1;
-                            return next_index;
+                            next_index
+                        }
+                        b'8' | b'9' => {
+                            // 8进制中不存在的数字，除非后面是小数点或指数
+                            self.state = State::State281;
+                            next_index
                         }
                         _ => {
-                            // 直接匹配数字整形0
-                            self.tokens.push(Token::Numbers(Number::Integer(0)));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.start_index + self.col - ptr_index,
-                            });
+                            // 直接匹配数字整形0，按照C语言标准它是8进制常量
                             self.state = State::Start;
-                            return ptr_index;
+                            self.push_integer(ptr_index, Radix::Octal)
                         }
                     }
                 }
-                State::State26 => match self.chars[ptr_index] {
+                State::State26 => match self.peek(ptr_index) {
                     // 主要确定十六进制数字不能是0x后面不跟数字
-                    '0'..='9' | 'a'..='f' | 'A'..='F' => {
+                    b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F' => {
                         self.state = State::State261; // State261继续处理16进制数
-                        self.col += 1;
-                        return next_index;
+                        next_index
+                    }
+                    b'\'' if self.digit_separators() => {
+                        self.digit_separator(ptr_index, u8::is_ascii_hexdigit)
+                    }
+                    b'.' => {
+                        self.state = State::State262; // 0x.8p1 整数部分可以为空
+                        next_index
                     }
                     _ => {
                         self.state = State::Start;
-                        self.errors.push(format!(
-                            "Error hexadecimal number in State26 in {}",
-                            self.start_index
-                        ));
-                        return ptr_index;
+                        self.push_malformed_number(
+                            LexError::MissingHexDigits(self.span(self.start_index, ptr_index)),
+                            ptr_index,
+                        )
                     }
                 },
-                State::State261 => match self.chars[ptr_index] {
-                    '0'..='9' | 'a'..='f' | 'A'..='F' => {
+                State::State261 => match self.peek(ptr_index) {
+                    b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F' => {
                         self.state = State::State261; // State261继续处理16进制数
-                        self.col += 1;
-                        return next_index;
+                        next_index
+                    }
+                    b'\'' if self.digit_separators() => {
+                        self.digit_separator(ptr_index, u8::is_ascii_hexdigit)
+                    }
+                    b'.' => {
+                        self.state = State::State262; // State262匹配16进制浮点数的小数部分
+                        next_index
+                    }
+                    b'p' | b'P' => {
+                        self.state = State::State23; // 16进制浮点数的二进制指数
+                        next_index
                     }
                     _ => {
                         self.state = State::Start;
-
-                        if self.chars[self.start_index] != '-' {
-                            let number_str: String =
-                                self.chars[self.start_index..ptr_index].iter().collect();
-                            if let Ok(number) = i64::from_str_radix(&number_str[2..], 16) {
-                                self.tokens
-                                    .push(Token::Numbers(Number::Integer(number as i64)));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.start_index + self.col - ptr_index,
-                                });
-                            } else {
-                                self.errors
-                                    .push(format!("Error number: {} in State261", number_str));
-                            }
-                        } else {
-                            let number_str: String =
-                                self.chars[self.start_index + 1..ptr_index].iter().collect();
-                            if let Ok(number) = i64::from_str_radix(&number_str[2..], 16) {
-                                let number_negetive = 0 - number;
-                                self.tokens
-                                    .push(Token::Numbers(Number::Integer(number_negetive as i64)));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.start_index + self.col - ptr_index,
-                                });
-                            } else {
-                                self.errors
-                                    .push(format!("Error number: {} in State261", number_str));
-                            }
-                        }
-                        return ptr_index;
+                        self.push_integer(ptr_index, Radix::Hexadecimal)
                     }
                 },
-                State::State27 => match self.chars[ptr_index] {
-                    '0' | '1' => {
-                        while self.chars[next_index] == '0' || self.chars[next_index] == '1' {
-                            // 直接使用循环，避免递归次数过多堆栈溢出(现已将整个词法处理程序更改为循环)
-                            next_index += 1;
-                            self.col += 1;
-                        }
-                        let number_str: String =
-                            self.chars[self.start_index..next_index].iter().collect();
-                        if let Ok(number) = i64::from_str_radix(&number_str[2..], 2) {
-                            self.tokens
-                                .push(Token::Numbers(Number::Integer(number as i64)));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.start_index + self.col - ptr_index,
-                            });
-                        } else {
-                            self.errors
-                                .push(format!("Error number: {} in State27", number_str));
-                        }
+                State::State262 => match self.peek(ptr_index) {
+                    b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F' => {
+                        self.state = State::State262;
+                        next_index
+                    }
+                    b'\'' if self.digit_separators() => {
+                        self.digit_separator(ptr_index, u8::is_ascii_hexdigit)
+                    }
+                    b'p' | b'P' => {
+                        self.state = State::State23;
+                        next_index
+                    }
+                    _ => {
+                        // 按照C语言标准，16进制浮点数必须带有 p 指数
                         self.state = State::Start;
-                        // self.col += 1;
-                        return next_index;
+                        self.push_malformed_number(
+                            LexError::MissingBinaryExponent(self.span(self.start_index, ptr_index)),
+                            ptr_index,
+                        )
+                    }
+                },
+                State::State27 => match self.peek(ptr_index) {
+                    b'0' | b'1' => {
+                        self.state = State::State271; // State271继续处理2进制数
+                        next_index
+                    }
+                    b'\'' if self.digit_separators() => {
+                        self.digit_separator(ptr_index, |c| matches!(c, b'0' | b'1'))
+                    }
+                    _ => {
+                        self.state = State::Start;
+                        self.push_malformed_number(
+                            LexError::MissingBinaryDigits(self.span(self.start_index, ptr_index)),
+                            ptr_index,
+                        )
+                    }
+                },
+                State::State271 => match self.peek(ptr_index) {
+                    b'0' | b'1' => {
+                        self.state = State::State271;
+                        next_index
+                    }
+                    b'\'' if self.digit_separators() => {
+                        self.digit_separator(ptr_index, |c| matches!(c, b'0' | b'1'))
                     }
                     _ => {
                         self.state = State::Start;
-                        let number_str: String =
-                            self.chars[self.start_index..next_index].iter().collect();
-                        self.errors
-                            .push(format!("Error number: {} in State27", number_str));
-                        return ptr_index;
+                        self.push_integer(ptr_index, Radix::Binary)
                     }
                 },
-                State::State28 => match self.chars[ptr_index] {
-                    '0'..='7' => {
+                State::State28 => match self.peek(ptr_index) {
+                    b'0'..=b'7' => {
                         self.state = State::State28; // State28继续处理8进制数
-                        self.col += 1;
-                        return next_index;
+                        next_index
+                    }
+                    b'\'' if self.digit_separators() => {
+                        self.digit_separator(ptr_index, u8::is_ascii_digit)
                     }
-                    '8' | '9' => {
+                    b'8' | b'9' => {
                         self.state = State::State281; // 进入到8进制异常处理State281
-                        self.col += 1;
-                        return next_index;
+                        next_index
                     }
-                    '.' => {
+                    b'.' => {
                         // 按照C语言标准，0开头但带小数点看作十进制浮点数
                         self.state = State::State21; // State20不匹配.和e,匹配完第一个字符转为State21
-                        self.col += 1;
-                        return next_index;
+                        next_index
                     }
-                    'e' | 'E' => {
+                    b'e' | b'E' => {
                         // 按照C语言标准，0开头但e看作十进制浮点数
                         self.state = State::State23; // State23不匹配e和.但匹配-或正
-                        self.col += 1;
-                        return next_index;
+                        next_index
                     }
                     _ => {
                         self.state = State::Start;
-                        let number_str: String =
-                            self.chars[self.start_index..ptr_index].iter().collect();
-                        // println!("{}", &number_str);
-                        if let Ok(number) = i64::from_str_radix(&number_str[1..], 8) {
-                            if self.chars[self.start_index] == '-' {
-                                self.tokens
-                                    .push(Token::Numbers(Number::Integer(0 - number as i64)));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.start_index + self.col - ptr_index,
-                                });
-                            } else {
-                                self.tokens
-                                    .push(Token::Numbers(Number::Integer(number as i64)));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.start_index + self.col - ptr_index,
-                                });
-                            }
-                        } else {
-                            self.errors
-                                .push(format!("Error number: {} in State28", number_str));
-                        }
-                        return ptr_index;
+                        self.push_integer(ptr_index, Radix::Octal)
                     }
                 },
                 State::State281 => {
-                    match self.chars[ptr_index] {
-                        '0'..='9' => {
+                    match self.peek(ptr_index) {
+                        b'0'..=b'9' => {
                             self.state = State::State281; // State281继续收集错误数字
-                            self.col += 1;
-                            return next_index;
+                            next_index
+                        }
+                        b'\'' if self.digit_separators() => {
+                            self.digit_separator(ptr_index, u8::is_ascii_digit)
+                        }
+                        b'.' => {
+                            // 08.5 这样的是十进制浮点数，不是错误的8进制数
+                            self.state = State::State21;
+                            next_index
+                        }
+                        b'e' | b'E' => {
+                            self.state = State::State23;
+                            next_index
                         }
                         _ => {
                             self.state = State::Start;
-                            let number_str: String =
-                                self.chars[self.start_index..next_index].iter().collect();
-                            self.errors
-                                .push(format!("Invalid octal number: {} in State28", number_str));
-                            return ptr_index;
+                            self.push_malformed_number(
+                                LexError::InvalidOctalDigit(self.span(self.start_index, ptr_index)),
+                                ptr_index,
+                            )
                         }
                     }
                 }
@@ -720,725 +2653,636 @@ pub mod lexer {
 
                 // }
                 State::State3 => {
-                    match self.chars[self.start_index] {
-                        '+' => {
-                            // 处理 '+' 的逻辑...
-                            match self.chars[ptr_index] {
-                                '=' => {
-                                    self.tokens.push(Token::Operators(Operator::AddAssign));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                    match self.peek(self.start_index) {
+                        b'+' => {
+                            // 处理 b'+' 的逻辑...
+                            match self.peek(ptr_index) {
+                                b'=' => {
+                                    self.push_token(
+                                        Token::Operators(Operator::AddAssign),
+                                        next_index,
+                                    );
                                     self.state = State::Start;
-                                    self.col += 1;
-                                    return next_index;
+                                    next_index
                                 }
-                                '+' => {
-                                    self.tokens.push(Token::Operators(Operator::Increment));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                                b'+' => {
+                                    self.push_token(
+                                        Token::Operators(Operator::Increment),
+                                        next_index,
+                                    );
                                     self.state = State::Start;
-                                    self.col += 1;
-                                    return next_index;
-                                }
-                                '0'..='9' => {
-                                    if matches!(
-                                        self.tokens.last(),
-                                        Some(Token::Identifiers(_))
-                                            | Some(Token::Delimiters(Delimiter::RightParenthesis))
-                                            | Some(Token::Numbers(_))
-                                    ) {
-                                        self.tokens.push(Token::Operators(Operator::Add));
-                                        self.tokens_location.push(TokenLocation {
-                                            row: self.row,
-                                            col: self.col - 1,
-                                        });
-                                        self.state = State::Start;
-                                        return ptr_index;
-                                    } else {
-                                        self.state = State::State2; // State2匹配负数
-                                        return ptr_index;
-                                    }
+                                    next_index
                                 }
                                 _ => {
-                                    self.tokens.push(Token::Operators(Operator::Add));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                                    self.push_token(Token::Operators(Operator::Plus), ptr_index);
                                     self.state = State::Start;
-                                    return ptr_index;
+                                    ptr_index
                                 }
                             }
                         }
-                        '-' => {
-                            // 处理 '-' 的逻辑...
-                            match self.chars[ptr_index] {
-                                '=' => {
-                                    self.tokens.push(Token::Operators(Operator::SubtractAssign));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                        b'-' => {
+                            // 处理 b'-' 的逻辑...
+                            match self.peek(ptr_index) {
+                                b'=' => {
+                                    self.push_token(
+                                        Token::Operators(Operator::SubtractAssign),
+                                        next_index,
+                                    );
                                     self.state = State::Start;
-                                    self.col += 1;
-                                    return next_index;
+                                    next_index
                                 }
-                                '-' => {
-                                    self.tokens.push(Token::Operators(Operator::Decrement));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                                b'-' => {
+                                    self.push_token(
+                                        Token::Operators(Operator::Decrement),
+                                        next_index,
+                                    );
                                     self.state = State::Start;
-                                    self.col += 1;
-                                    return next_index;
+                                    next_index
                                 }
-                                '>' => {
-                                    self.tokens
-                                        .push(Token::Operators(Operator::PointerMemberAccess));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                                b'>' if self.language == Language::Cpp
+                                    && self.peek(next_index) == b'*' =>
+                                {
+                                    self.push_token(
+                                        Token::Operators(Operator::PointerMemberPointerAccess),
+                                        next_index + 1,
+                                    );
                                     self.state = State::Start;
-                                    self.col += 1;
-                                    return next_index;
+                                    next_index + 1
                                 }
-                                '0'..='9' => {
-                                    if matches!(
-                                        self.tokens.last(),
-                                        Some(Token::Identifiers(_))
-                                            | Some(Token::Delimiters(Delimiter::RightParenthesis))
-                                            | Some(Token::Numbers(_))
-                                    ) {
-                                        self.tokens.push(Token::Operators(Operator::Subtract));
-                                        self.tokens_location.push(TokenLocation {
-                                            row: self.row,
-                                            col: self.col - 1,
-                                        });
-                                        self.state = State::Start;
-                                        return ptr_index;
-                                    } else {
-                                        self.state = State::State2; // State2匹配负数
-                                        return ptr_index;
-                                    }
+                                b'>' => {
+                                    self.push_token(
+                                        Token::Operators(Operator::PointerMemberAccess),
+                                        next_index,
+                                    );
+                                    self.state = State::Start;
+                                    next_index
                                 }
                                 _ => {
-                                    self.tokens.push(Token::Operators(Operator::Subtract));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                                    self.push_token(Token::Operators(Operator::Minus), ptr_index);
                                     self.state = State::Start;
-                                    return ptr_index;
+                                    ptr_index
                                 }
                             }
                         }
-                        '*' => {
-                            // 处理 '*' 的逻辑...
-                            match self.chars[ptr_index] {
-                                '=' => {
-                                    self.tokens.push(Token::Operators(Operator::MultiplyAssign));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                        b'*' => {
+                            // 处理 b'*' 的逻辑...
+                            match self.peek(ptr_index) {
+                                b'=' => {
+                                    self.push_token(
+                                        Token::Operators(Operator::MultiplyAssign),
+                                        next_index,
+                                    );
                                     self.state = State::Start;
-                                    self.col += 1;
-                                    return next_index;
+                                    next_index
                                 }
                                 _ => {
-                                    if matches!(
-                                        self.tokens.last(),
-                                        Some(Token::Identifiers(_))
-                                            | Some(Token::Delimiters(Delimiter::RightParenthesis))
-                                            | Some(Token::Numbers(_))
-                                    ) {
-                                        self.tokens.push(Token::Operators(Operator::Multiply));
-                                        self.tokens_location.push(TokenLocation {
-                                            row: self.row,
-                                            col: self.col - 1,
-                                        });
-                                        self.state = State::Start;
-                                        return ptr_index;
-                                    } else {
-                                        self.tokens.push(Token::Operators(Operator::Dereference));
-                                        self.tokens_location.push(TokenLocation {
-                                            row: self.row,
-                                            col: self.col - 1,
-                                        });
-                                        self.state = State::Start;
-                                        return ptr_index;
-                                    }
+                                    self.push_token(Token::Operators(Operator::Star), ptr_index);
+                                    self.state = State::Start;
+                                    ptr_index
                                 }
                             }
                         }
-                        '/' => {
-                            // 处理 '/' 的逻辑...
-                            match self.chars[ptr_index] {
-                                '=' => {
-                                    self.tokens.push(Token::Operators(Operator::DivideAssign));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                        b'/' => {
+                            // 处理 b'/' 的逻辑...
+                            match self.peek(ptr_index) {
+                                b'=' => {
+                                    self.push_token(
+                                        Token::Operators(Operator::DivideAssign),
+                                        next_index,
+                                    );
                                     self.state = State::Start;
-                                    self.col += 1;
-                                    return next_index;
+                                    next_index
                                 }
-                                '/' => {
-                                    while self.chars[next_index] != '\n' {
-                                        self.col += 1;
+                                b'/' => {
+                                    while self.has_byte(next_index)
+                                        && self.peek(next_index) != b'\n'
+                                    {
                                         next_index += 1;
+                                        self.retain_from = next_index;
                                     }
-                                    self.row += 1;
                                     self.state = State::Start;
-                                    // self.col += 1;
-                                    return next_index;
+                                    next_index
                                 } // 处理注释
-                                '*' => {
+                                b'*' => {
                                     self.state = State::State33;
-                                    self.col += 1;
-                                    return next_index;
+                                    next_index
                                 } // 处理注释
                                 _ => {
-                                    self.tokens.push(Token::Operators(Operator::Divide));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                                    self.push_token(Token::Operators(Operator::Divide), ptr_index);
                                     self.state = State::Start;
-                                    return ptr_index;
+                                    ptr_index
                                 }
                             }
                         }
-                        '%' => match self.chars[ptr_index] {
-                            '=' => {
-                                self.tokens.push(Token::Operators(Operator::ModulusAssign));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.col - 1,
-                                });
+                        b'%' => match self.peek(ptr_index) {
+                            b'=' => {
+                                self.push_token(
+                                    Token::Operators(Operator::ModulusAssign),
+                                    next_index,
+                                );
+                                self.state = State::Start;
+                                next_index
+                            }
+                            b'>' => {
+                                // 双字符组 %> 即 }
+                                self.push_token(
+                                    Token::Delimiters(Delimiter::RightBrace),
+                                    next_index,
+                                );
                                 self.state = State::Start;
-                                self.col += 1;
-                                return next_index;
+                                next_index
+                            }
+                            b':' => {
+                                // 双字符组 %: 即 #，%:%: 即 ##
+                                self.state = State::Start;
+                                if self.peek(next_index) == b'%'
+                                    && self.peek(next_index + 1) == b':'
+                                {
+                                    self.push_token(
+                                        Token::Delimiters(Delimiter::HashHash),
+                                        next_index + 2,
+                                    );
+                                    return next_index + 2;
+                                }
+                                self.push_hash(next_index);
+                                next_index
                             }
                             _ => {
-                                self.tokens.push(Token::Operators(Operator::Modulus));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.col - 1,
-                                });
+                                self.push_token(Token::Operators(Operator::Modulus), ptr_index);
                                 self.state = State::Start;
-                                return ptr_index;
+                                ptr_index
                             }
                         },
-                        '=' => {
-                            // 处理 '=' 的逻辑...
-                            match self.chars[ptr_index] {
-                                '=' => {
-                                    self.tokens.push(Token::Operators(Operator::Equal));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                        b'=' => {
+                            // 处理 b'=' 的逻辑...
+                            match self.peek(ptr_index) {
+                                b'=' => {
+                                    self.push_token(Token::Operators(Operator::Equal), next_index);
                                     self.state = State::Start;
-                                    self.col += 1;
-                                    return next_index;
+                                    next_index
                                 }
                                 _ => {
-                                    self.tokens.push(Token::Operators(Operator::Assign));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                                    self.push_token(Token::Operators(Operator::Assign), ptr_index);
                                     self.state = State::Start;
-                                    return ptr_index;
+                                    ptr_index
                                 }
                             }
                         }
-                        '!' => {
-                            // 处理 '!' 的逻辑...
-                            match self.chars[ptr_index] {
-                                '=' => {
-                                    self.tokens.push(Token::Operators(Operator::NotEqual));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                        b'!' => {
+                            // 处理 b'!' 的逻辑...
+                            match self.peek(ptr_index) {
+                                b'=' => {
+                                    self.push_token(
+                                        Token::Operators(Operator::NotEqual),
+                                        next_index,
+                                    );
                                     self.state = State::Start;
-                                    self.col += 1;
-                                    return next_index;
+                                    next_index
                                 }
                                 _ => {
-                                    self.tokens.push(Token::Operators(Operator::LogicalNot));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                                    self.push_token(
+                                        Token::Operators(Operator::LogicalNot),
+                                        ptr_index,
+                                    );
                                     self.state = State::Start;
-                                    return ptr_index;
+                                    ptr_index
                                 }
                             }
                         }
-                        '<' => {
-                            // 处理 '<' 的逻辑...
-                            match self.chars[ptr_index] {
-                                '=' => {
-                                    self.tokens
-                                        .push(Token::Operators(Operator::LessThanOrEqual));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                        b'<' => {
+                            // 处理 b'<' 的逻辑...
+                            match self.peek(ptr_index) {
+                                b'=' if self.language == Language::Cpp
+                                    && self.peek(next_index) == b'>' =>
+                                {
+                                    self.push_token(
+                                        Token::Operators(Operator::ThreeWayCompare),
+                                        next_index + 1,
+                                    );
+                                    self.state = State::Start;
+                                    next_index + 1
+                                }
+                                b'=' => {
+                                    self.push_token(
+                                        Token::Operators(Operator::LessThanOrEqual),
+                                        next_index,
+                                    );
                                     self.state = State::Start;
-                                    self.col += 1;
-                                    return next_index;
+                                    next_index
                                 }
-                                '<' => {
+                                b'<' => {
                                     self.state = State::State31; // State31可匹配<<=
-                                    self.col += 1;
-                                    return next_index;
+                                    next_index
+                                }
+                                b':' if self.language == Language::Cpp
+                                    && self.peek(next_index) == b':'
+                                    && !matches!(self.peek(next_index + 1), b':' | b'>') =>
+                                {
+                                    // C++ 中 <:: 后面不是 : 或 > 时按 < :: 识别，以便写 vector<::std::string>
+                                    self.push_token(
+                                        Token::Operators(Operator::LessThan),
+                                        ptr_index,
+                                    );
+                                    self.state = State::Start;
+                                    ptr_index
+                                }
+                                b':' => {
+                                    // 双字符组 <: 即 [
+                                    self.push_token(
+                                        Token::Delimiters(Delimiter::LeftBracket),
+                                        next_index,
+                                    );
+                                    self.state = State::Start;
+                                    next_index
+                                }
+                                b'%' => {
+                                    // 双字符组 <% 即 {
+                                    self.push_token(
+                                        Token::Delimiters(Delimiter::LeftBrace),
+                                        next_index,
+                                    );
+                                    self.state = State::Start;
+                                    next_index
                                 }
                                 _ => {
-                                    self.tokens.push(Token::Operators(Operator::LessThan));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                                    self.push_token(
+                                        Token::Operators(Operator::LessThan),
+                                        ptr_index,
+                                    );
                                     self.state = State::Start;
-                                    return ptr_index;
+                                    ptr_index
                                 }
                             }
                         }
-                        '>' => {
-                            // 处理 '>' 的逻辑...
-                            match self.chars[ptr_index] {
-                                '=' => {
-                                    self.tokens
-                                        .push(Token::Operators(Operator::GreaterThanOrEqual));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                        b'>' => {
+                            // 处理 b'>' 的逻辑...
+                            match self.peek(ptr_index) {
+                                b'=' => {
+                                    self.push_token(
+                                        Token::Operators(Operator::GreaterThanOrEqual),
+                                        next_index,
+                                    );
                                     self.state = State::Start;
-                                    self.col += 1;
-                                    return next_index;
+                                    next_index
                                 }
-                                '>' => {
+                                b'>' => {
                                     self.state = State::State32; // State32可匹配>>=
-                                    self.col += 1;
-                                    return next_index;
+                                    next_index
                                 }
                                 _ => {
-                                    self.tokens.push(Token::Operators(Operator::GreaterThan));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
+                                    self.push_token(
+                                        Token::Operators(Operator::GreaterThan),
+                                        ptr_index,
+                                    );
                                     self.state = State::Start;
-                                    return ptr_index;
+                                    ptr_index
                                 }
                             }
                         }
-                        '&' => match self.chars[ptr_index] {
-                            '=' => {
-                                self.tokens
-                                    .push(Token::Operators(Operator::BitwiseAndAssign));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.col,
-                                });
+                        b'&' => match self.peek(ptr_index) {
+                            b'=' => {
+                                self.push_token(
+                                    Token::Operators(Operator::BitwiseAndAssign),
+                                    next_index,
+                                );
                                 self.state = State::Start;
-                                self.col += 1;
-                                return next_index;
+                                next_index
                             }
-                            '&' => {
-                                self.tokens.push(Token::Operators(Operator::LogicalAnd));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.col - 1,
-                                });
+                            b'&' => {
+                                self.push_token(Token::Operators(Operator::LogicalAnd), next_index);
                                 self.state = State::Start;
-                                return ptr_index;
+                                next_index
                             }
                             _ => {
-                                if matches!(
-                                    self.tokens.last(),
-                                    Some(Token::Identifiers(_))
-                                        | Some(Token::Delimiters(Delimiter::RightParenthesis))
-                                        | Some(Token::Strings(_))
-                                ) {
-                                    self.tokens.push(Token::Operators(Operator::BitwiseAnd));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
-                                    self.state = State::Start;
-                                    self.col += 1;
-                                    return next_index;
-                                } else {
-                                    self.tokens.push(Token::Operators(Operator::AddressOf));
-                                    self.tokens_location.push(TokenLocation {
-                                        row: self.row,
-                                        col: self.col - 1,
-                                    });
-                                    self.state = State::Start;
-                                    self.col += 1;
-                                    return next_index;
-                                }
+                                self.push_token(Token::Operators(Operator::Amp), ptr_index);
+                                self.state = State::Start;
+                                ptr_index
                             }
                         },
-                        '|' => match self.chars[ptr_index] {
-                            '=' => {
-                                self.tokens
-                                    .push(Token::Operators(Operator::BitwiseOrAssign));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.col - 1,
-                                });
+                        b'|' => match self.peek(ptr_index) {
+                            b'=' => {
+                                self.push_token(
+                                    Token::Operators(Operator::BitwiseOrAssign),
+                                    next_index,
+                                );
                                 self.state = State::Start;
-                                self.col += 1;
-                                return next_index;
+                                next_index
                             }
-                            '|' => {
-                                self.tokens.push(Token::Operators(Operator::LogicalOr));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.col - 1,
-                                });
+                            b'|' => {
+                                self.push_token(Token::Operators(Operator::LogicalOr), next_index);
                                 self.state = State::Start;
-                                self.col += 1;
-                                return next_index;
+                                next_index
                             }
                             _ => {
-                                self.tokens.push(Token::Operators(Operator::BitwiseOr));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.col - 1,
-                                });
+                                self.push_token(Token::Operators(Operator::BitwiseOr), ptr_index);
                                 self.state = State::Start;
-                                return ptr_index;
+                                ptr_index
                             }
                         },
-                        '^' => match self.chars[ptr_index] {
-                            '=' => {
-                                self.tokens
-                                    .push(Token::Operators(Operator::BitwiseXorAssign));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.col - 1,
-                                });
+                        b'^' => match self.peek(ptr_index) {
+                            b'=' => {
+                                self.push_token(
+                                    Token::Operators(Operator::BitwiseXorAssign),
+                                    next_index,
+                                );
                                 self.state = State::Start;
-                                self.col += 1;
-                                return next_index;
+                                next_index
                             }
                             _ => {
-                                self.tokens.push(Token::Operators(Operator::BitwiseXor));
-                                self.tokens_location.push(TokenLocation {
-                                    row: self.row,
-                                    col: self.col - 1,
-                                });
+                                self.push_token(Token::Operators(Operator::BitwiseXor), ptr_index);
                                 self.state = State::Start;
-                                return ptr_index;
+                                ptr_index
                             }
                         },
-                        '.' => {
-                            self.tokens.push(Token::Operators(Operator::MemberAccess));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.col - 1,
-                            });
+                        b'.' if self.language == Language::Cpp && self.peek(ptr_index) == b'*' => {
+                            self.push_token(
+                                Token::Operators(Operator::MemberPointerAccess),
+                                next_index,
+                            );
                             self.state = State::Start;
-                            return ptr_index;
+                            next_index
                         }
-                        '~' => {
-                            self.tokens.push(Token::Operators(Operator::BitwiseNot));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.col - 1,
-                            });
+                        b'.' if self.peek(ptr_index) == b'.' && self.peek(next_index) == b'.' => {
+                            // 可变参数的省略号
+                            self.push_token(Token::Delimiters(Delimiter::Ellipsis), next_index + 1);
+                            self.state = State::Start;
+                            next_index + 1
+                        }
+                        b'.' => {
+                            self.push_token(Token::Operators(Operator::MemberAccess), ptr_index);
+                            self.state = State::Start;
+                            ptr_index
+                        }
+                        b'~' => {
+                            self.push_token(Token::Operators(Operator::BitwiseNot), ptr_index);
                             self.state = State::Start;
-                            return ptr_index;
+                            ptr_index
                         }
                         _ => {
-                            self.errors.push(format!(
-                                "Unkown key: {} in {} (State::State3)",
-                                self.chars[ptr_index], ptr_index
-                            ));
-                            self.col += 1;
-                            return next_index;
+                            let ch = self.char_at(self.start_index);
+                            self.push_error(LexError::UnknownCharacter {
+                                ch,
+                                span: self.span(self.start_index, ptr_index),
+                            });
+                            next_index
                         }
                     }
                 }
-                State::State31 => match self.chars[ptr_index] {
-                    '=' => {
-                        self.tokens
-                            .push(Token::Operators(Operator::LeftShiftAssign));
-                        self.tokens_location.push(TokenLocation {
-                            row: self.row,
-                            col: self.col - 2,
-                        });
+                State::State31 => match self.peek(ptr_index) {
+                    b'=' => {
+                        self.push_token(Token::Operators(Operator::LeftShiftAssign), next_index);
                         self.state = State::Start;
-                        self.col += 1;
-                        return next_index;
+                        next_index
                     }
                     _ => {
-                        self.tokens.push(Token::Operators(Operator::LeftShift));
-                        self.tokens_location.push(TokenLocation {
-                            row: self.row,
-                            col: self.col - 1,
-                        });
+                        self.push_token(Token::Operators(Operator::LeftShift), ptr_index);
                         self.state = State::Start;
-                        return ptr_index;
+                        ptr_index
                     }
                 },
-                State::State32 => match self.chars[ptr_index] {
-                    '=' => {
-                        self.tokens
-                            .push(Token::Operators(Operator::RightShiftAssign));
-                        self.tokens_location.push(TokenLocation {
-                            row: self.row,
-                            col: self.col - 2,
-                        });
+                State::State32 => match self.peek(ptr_index) {
+                    b'=' => {
+                        self.push_token(Token::Operators(Operator::RightShiftAssign), next_index);
                         self.state = State::Start;
-                        self.col += 1;
-                        return next_index;
+                        next_index
                     }
                     _ => {
-                        self.tokens.push(Token::Operators(Operator::RightShift));
-                        self.tokens_location.push(TokenLocation {
-                            row: self.row,
-                            col: self.col - 1,
-                        });
+                        self.push_token(Token::Operators(Operator::RightShift), ptr_index);
                         self.state = State::Start;
-                        return ptr_index;
+                        ptr_index
                     }
                 },
                 State::State33 => {
                     //处理/* */注释
-                    match self.chars[ptr_index] {
-                        '*' => match self.chars[next_index] {
-                            '/' => {
+                    match self.peek(ptr_index) {
+                        _ if !self.has_byte(ptr_index) => {
+                            // 注释直到输入末尾都没有结束，在开头的 /* 处报告
+                            self.push_error(LexError::UnterminatedComment(
+                                self.span(self.start_index, self.start_index + 2),
+                            ));
+                            self.state = State::Start;
+                            ptr_index
+                        }
+                        b'*' => match self.peek(next_index) {
+                            b'/' => {
                                 self.state = State::Start;
-                                self.col += 2;
-                                return next_index + 1;
+                                next_index + 1
                             }
                             _ => {
                                 self.state = State::State33;
-                                self.col += 1;
-                                return next_index;
+                                next_index
                             }
                         },
-                        '\n' => {
-                            self.col = 1;
-                            self.row += 1;
+                        b'\n' => {
                             self.state = State::State33;
-                            return next_index;
+                            next_index
                         }
                         _ => {
                             self.state = State::State33;
-                            self.col += 1;
-                            return next_index;
+                            next_index
                         }
                     }
                 }
                 State::State4 => {
-                    match self.chars[self.start_index] {
-                        ';' => {
+                    match self.peek(self.start_index) {
+                        b';' => {
                             // 处理分号的逻辑...
-                            self.tokens.push(Token::Delimiters(Delimiter::Semicolon));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.col - 1,
-                            });
+                            self.push_token(Token::Delimiters(Delimiter::Semicolon), ptr_index);
                             self.state = State::Start;
-                            return ptr_index;
+                            ptr_index
                         }
-                        ',' => {
+                        b',' => {
                             // 处理逗号的逻辑...
-                            self.tokens.push(Token::Delimiters(Delimiter::Comma));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.col - 1,
-                            });
+                            self.push_token(Token::Delimiters(Delimiter::Comma), ptr_index);
                             self.state = State::Start;
-                            return ptr_index;
+                            ptr_index
                         }
-                        '(' => {
+                        b'(' => {
                             // 处理左括号的逻辑...
-                            self.tokens
-                                .push(Token::Delimiters(Delimiter::LeftParenthesis));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.col - 1,
-                            });
+                            self.push_token(
+                                Token::Delimiters(Delimiter::LeftParenthesis),
+                                ptr_index,
+                            );
                             self.state = State::Start;
-                            return ptr_index;
+                            ptr_index
                         }
-                        ')' => {
+                        b')' => {
                             // 处理右括号的逻辑...
-                            self.tokens
-                                .push(Token::Delimiters(Delimiter::RightParenthesis));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.col - 1,
-                            });
+                            self.push_token(
+                                Token::Delimiters(Delimiter::RightParenthesis),
+                                ptr_index,
+                            );
                             self.state = State::Start;
-                            return ptr_index;
+                            ptr_index
                         }
-                        '[' => {
+                        b'[' => {
                             // 处理左方括号的逻辑...
-                            self.tokens.push(Token::Delimiters(Delimiter::LeftBracket));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.col - 1,
-                            });
+                            self.push_token(Token::Delimiters(Delimiter::LeftBracket), ptr_index);
                             self.state = State::Start;
-                            return ptr_index;
+                            ptr_index
                         }
-                        ']' => {
+                        b']' => {
                             // 处理右方括号的逻辑...
-                            self.tokens.push(Token::Delimiters(Delimiter::RightBracket));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.col - 1,
-                            });
+                            self.push_token(Token::Delimiters(Delimiter::RightBracket), ptr_index);
                             self.state = State::Start;
-                            return ptr_index;
+                            ptr_index
                         }
-                        '{' => {
+                        b'{' => {
                             // 处理左大括号的逻辑...
-                            self.tokens.push(Token::Delimiters(Delimiter::LeftBrace));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.col - 1,
-                            });
+                            self.push_token(Token::Delimiters(Delimiter::LeftBrace), ptr_index);
                             self.state = State::Start;
-                            return ptr_index;
+                            ptr_index
                         }
-                        '}' => {
+                        b'}' => {
                             // 处理右大括号的逻辑...
-                            self.tokens.push(Token::Delimiters(Delimiter::RightBrace));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.col - 1,
-                            });
-                            self.state = State::Start;
-                            return ptr_index;
-                        }
-                        '\\' => {
-                            self.tokens.push(Token::Delimiters(Delimiter::Backslash));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.col - 1,
-                            });
+                            self.push_token(Token::Delimiters(Delimiter::RightBrace), ptr_index);
                             self.state = State::Start;
-                            return ptr_index;
+                            ptr_index
                         }
-                        '\'' => {
-                            // 处理单引号的逻辑...
+                        b'\'' => {
+                            // 处理单引号的逻辑，引号后的第一个字符交给 State41 检查
                             self.state = State::State41;
-                            self.col += 1;
-                            return next_index;
+                            ptr_index
                         }
-                        '"' => {
-                            // 处理双引号的逻辑...
+                        b'"' => {
+                            // 处理双引号的逻辑，引号后的第一个字符交给 State42 检查
                             self.state = State::State42;
-                            self.col += 1;
-                            return next_index;
-                        }
-                        ':' => {
-                            self.tokens
-                                .push(Token::Delimiters(Delimiter::ConditionalSeparator));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.col,
-                            });
+                            ptr_index
+                        }
+                        b':' if self.peek(ptr_index) == b':'
+                            && (self.language == Language::Cpp
+                                || self.standard >= LanguageStandard::C23) =>
+                        {
+                            // :: 优先于双字符组 :>，::> 识别为 :: >
+                            self.push_token(
+                                Token::Operators(Operator::ScopeResolution),
+                                next_index,
+                            );
                             self.state = State::Start;
-                            return ptr_index;
-                        }
-                        '?' => {
-                            self.tokens
-                                .push(Token::Delimiters(Delimiter::ConditionalOperator));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.col,
-                            });
+                            next_index
+                        }
+                        b':' if self.peek(ptr_index) == b'>' => {
+                            // 双字符组 :> 即 ]
+                            self.push_token(Token::Delimiters(Delimiter::RightBracket), next_index);
+                            self.state = State::Start;
+                            next_index
+                        }
+                        b':' => {
+                            self.push_token(
+                                Token::Delimiters(Delimiter::ConditionalSeparator),
+                                ptr_index,
+                            );
+                            self.state = State::Start;
+                            ptr_index
+                        }
+                        b'?' => {
+                            self.push_token(
+                                Token::Delimiters(Delimiter::ConditionalOperator),
+                                ptr_index,
+                            );
                             self.state = State::Start;
-                            return ptr_index;
+                            ptr_index
                         }
                         _ => {
-                            self.errors.push(format!(
-                                "Unkown key: {} in {}",
-                                self.chars[ptr_index], ptr_index
-                            ));
-                            self.col += 1;
-                            return next_index;
+                            let ch = self.char_at(self.start_index);
+                            self.push_error(LexError::UnknownCharacter {
+                                ch,
+                                span: self.span(self.start_index, ptr_index),
+                            });
+                            next_index
                         }
                     }
                 }
                 State::State41 => {
                     //处理单引号
-                    match self.chars[ptr_index] {
-                        '\'' => {
-                            let str_slice: String =
-                                self.chars[self.start_index..next_index].iter().collect();
-                            self.tokens.push(Token::Strings(str_slice));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.start_index + self.col - ptr_index,
-                            });
+                    match self.peek(ptr_index) {
+                        b'\'' => {
                             self.state = State::Start;
-                            self.col += 1;
-                            return next_index;
+                            self.push_char_constant(next_index)
+                        }
+                        b'\\' if self.has_byte(next_index) && self.peek(next_index) != b'\n' => {
+                            // 跳过被转义的字符，避免 '\'' 提前结束
+                            self.state = State::State41;
+                            next_index + 1
+                        }
+                        c if c == b'\n'
+                            || (c == b'\r' && self.peek(next_index) == b'\n')
+                            || !self.has_byte(ptr_index) =>
+                        {
+                            // 字符常量不能跨行，未闭合时在行尾或输入末尾结束，换行交给 Start 处理
+                            let error =
+                                LexError::UnterminatedChar(self.span(self.start_index, ptr_index));
+                            self.push_malformed(error, ptr_index);
+                            self.state = State::Start;
+                            ptr_index
                         }
-
                         _ => {
-                            if next_index == self.chars_len {
-                                self.errors.push(format!("Unmatched key: '"));
-                                return self.chars_len;
-                            } else {
-                                self.state = State::State41;
-                                self.col += 1;
-                                return next_index;
-                            }
+                            self.state = State::State41;
+                            next_index
                         }
                     }
                 }
                 State::State42 => {
                     //处理双引号
-                    match self.chars[ptr_index] {
-                        '"' => {
-                            let str_slice: String =
-                                self.chars[self.start_index..next_index].iter().collect();
-                            self.tokens.push(Token::Strings(str_slice));
-                            self.tokens_location.push(TokenLocation {
-                                row: self.row,
-                                col: self.start_index + self.col - ptr_index,
-                            });
+                    match self.peek(ptr_index) {
+                        b'"' => {
+                            let raw = self.slice(self.start_index, next_index);
+                            let encoding = self.encoding;
+                            let body_start = encoding.prefix().len() + 1;
+                            let value = self.decode_literal(
+                                &raw[body_start..raw.len() - 1],
+                                self.start_index + body_start,
+                                encoding,
+                            );
+                            self.encoding = Encoding::Plain;
+                            let end = self.user_suffix_end(next_index);
+                            let suffix = (end > next_index).then(|| self.slice(next_index, end));
+                            self.push_literal(
+                                Token::Strings(StringLiteral {
+                                    encoding,
+                                    raw,
+                                    value,
+                                }),
+                                suffix,
+                                end,
+                            );
                             self.state = State::Start;
-                            self.col += 1;
-                            return next_index;
+                            end
+                        }
+                        b'\\' if self.has_byte(next_index) && self.peek(next_index) != b'\n' => {
+                            // 跳过被转义的字符，避免 "a\"b" 提前结束
+                            self.state = State::State42;
+                            next_index + 1
+                        }
+                        c if c == b'\n'
+                            || (c == b'\r' && self.peek(next_index) == b'\n')
+                            || !self.has_byte(ptr_index) =>
+                        {
+                            // 字符串不能跨行，未闭合时在行尾或输入末尾结束，换行交给 Start 处理
+                            let error = LexError::UnterminatedString(
+                                self.span(self.start_index, ptr_index),
+                            );
+                            self.push_malformed(error, ptr_index);
+                            self.state = State::Start;
+                            ptr_index
                         }
-
                         _ => {
-                            if next_index == self.chars_len {
-                                self.errors.push(format!("Unmatched key: \""));
-                                return self.chars_len;
-                            } else {
-                                self.state = State::State42;
-                                self.col += 1;
-                                return next_index;
-                            }
+                            self.state = State::State42;
+                            next_index
                         }
                     }
                 }
             }
         }
     }
-}
\ No newline at end of file
+
+    impl<'src> Iterator for Lexer<'src> {
+        type Item = Result<(Token<'src>, Span), LexError>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            self.next_token()
+        }
+    }
+}
+
+pub mod preprocessor;
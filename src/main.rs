@@ -1,25 +1,111 @@
-#![allow(special_module_name)]
-mod lib;
-use lib::lexer::Lexer;
+use lexer::lexer::{Language, LanguageStandard};
+use lexer::preprocessor::Preprocessor;
+use std::env;
 
 fn main() {
-    let filename = "source.c"; // 你可以将文件名作为输入参数，也可以修改 lex 方法以接受文件路径
-    let file_content = std::fs::read_to_string(filename)
-        .expect("Failed to read file")
-        .to_string();
-    // file_content = preprocesself.tokens.pushs(&file_content);
-    // println!("{file_content}");
-    let mut lexer = Lexer::new(&file_content);
-    match lexer.lex() {
-        Ok((tokens, tokens_location, errors)) => {
-            for (token, location) in tokens.iter().zip(tokens_location.iter()) {
-                println!("Tokens: {:?}, Location: {:?}", token, location);
+    // 用法：lexer [-I 目录] [-isystem 目录] [-D 宏[(参数)][=值]] [-U 宏] [-std=c89|c99|c11|c17|c23] [-x c|c++] [-trigraphs] [--partial] [文件名]，默认处理 source.c
+    // --partial 只处理由 -D、-U 给出的宏决定的条件编译，输出处理后的源码
+    let mut preprocessor = Preprocessor::new();
+    let mut filename = String::from("source.c");
+    let mut partial = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-I" | "-isystem" | "-D" | "-U" => {
+                let Some(value) = args.next() else {
+                    eprintln!("Error: missing argument after {}", arg);
+                    return;
+                };
+                preprocessor = apply_option(preprocessor, &arg, &value);
             }
-            // 处理错误信息
+            "-x" => {
+                let language = match args.next().as_deref() {
+                    Some("c") => Language::C,
+                    Some("c++") => Language::Cpp,
+                    other => {
+                        eprintln!("Error: unknown language {}", other.unwrap_or(""));
+                        return;
+                    }
+                };
+                preprocessor = preprocessor.with_language(language);
+            }
+            "--partial" => partial = true,
+            "-trigraphs" => preprocessor = preprocessor.with_trigraphs(true),
+            _ if arg.starts_with("-std=") => {
+                let standard = match &arg["-std=".len()..] {
+                    "c89" | "c90" => LanguageStandard::C89,
+                    "c99" => LanguageStandard::C99,
+                    "c11" => LanguageStandard::C11,
+                    "c17" | "c18" => LanguageStandard::C17,
+                    "c23" => LanguageStandard::C23,
+                    other => {
+                        eprintln!("Error: unknown language standard {}", other);
+                        return;
+                    }
+                };
+                preprocessor = preprocessor.with_standard(standard);
+            }
+            _ => match ["-I", "-D", "-U"]
+                .into_iter()
+                .find(|option| arg.starts_with(option))
+            {
+                Some(option) => {
+                    preprocessor = apply_option(preprocessor, option, &arg[option.len()..])
+                }
+                None => filename = arg,
+            },
+        }
+    }
+    if partial {
+        match preprocessor.preprocess_partial_file(&filename) {
+            Ok((output, errors)) => {
+                print!("{}", output);
+                for err in errors {
+                    eprintln!("{}", preprocessor.format_diagnostic(&err));
+                }
+            }
+            Err(err) => eprintln!("Error: {}: {}", filename, err),
+        }
+        return;
+    }
+    match preprocessor.preprocess_file(&filename) {
+        Ok((tokens, errors)) => {
+            for token in tokens.iter() {
+                println!("Tokens: {:?}, Location: {:?}", token.token, token.location);
+            }
+            // 处理错误信息，头文件中的错误会带上包含链
             for err in errors {
-                eprintln!("Error: {}", err);
+                eprintln!("{}", preprocessor.format_diagnostic(&err));
             }
         }
-        Err(err) => eprintln!("Error: {}", err),
+        Err(err) => eprintln!("Error: {}: {}", filename, err),
+    }
+}
+
+// 处理带参数的命令行选项，-D 宏 相当于 -D 宏=1，宏名后可以带参数列表
+fn apply_option(mut preprocessor: Preprocessor, option: &str, value: &str) -> Preprocessor {
+    match option {
+        "-I" => preprocessor.with_include_dir(value),
+        "-isystem" => preprocessor.with_system_include_dir(value),
+        "-D" => {
+            // 函数式宏写作 -D'F(x)=x+1'，= 在参数列表之后
+            let params_end = match (value.find('('), value.find('=')) {
+                (Some(open), Some(equals)) if open < equals => value.find(')').unwrap_or(open),
+                _ => 0,
+            };
+            let (name, body) = match value[params_end..].find('=') {
+                Some(equals) => value.split_at(params_end + equals),
+                None => (value, "=1"),
+            };
+            let body = &body[1..];
+            for err in preprocessor.define(name, body) {
+                eprintln!("{}", preprocessor.format_diagnostic(&err));
+            }
+            preprocessor
+        }
+        _ => {
+            preprocessor.undefine(value);
+            preprocessor
+        }
     }
 }
@@ -0,0 +1,2515 @@
+// 预处理器：在词法分析得到的 token 流之上处理预处理指令并展开宏
+use crate::lexer::{
+    spelling, Delimiter, Encoding, IntegerConstant, IntegerSuffix, IntegerType, Language,
+    LanguageStandard, LexError, Lexer, Number, Operator, Radix, Severity, StringLiteral, Token,
+    TokenLocation,
+};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// -D 定义的宏与 ## 拼接等重新切分的文本所使用的文件编号
+const BUILTIN_FILE: usize = usize::MAX;
+
+// 预处理之后的 token，location 指向它在源码中的原始位置
+#[derive(Debug, Clone)]
+pub struct PpToken {
+    pub token: Token<'static>,
+    pub location: TokenLocation,
+    pub expanded_from: Option<TokenLocation>, // 由宏展开得到时，最外层宏调用的位置
+    pub spelling: String,                     // token 的源码文本，用于字符串化与拼接
+    pub leading_space: bool,                  // 前面是否有空白
+    hide_set: Rc<HashSet<String>>,            // 展开过程中不能再展开的宏名
+}
+
+impl PpToken {
+    fn macro_name(&self) -> Option<&str> {
+        match self.token {
+            // 标识符按规范化后的名字比较，\u00e9 与 é 是同一个宏
+            Token::Identifiers(ref name) => Some(name),
+            Token::Keywords(_) => Some(&self.spelling),
+            _ => None,
+        }
+    }
+
+    fn is_delimiter(&self, delimiter: Delimiter) -> bool {
+        self.token == Token::Delimiters(delimiter)
+    }
+}
+
+// 预处理过程中的诊断，location 为出错的 token 位置
+#[derive(Debug, Clone)]
+pub enum PreprocessError {
+    Lex {
+        error: LexError,
+        location: TokenLocation,
+    }, // 词法分析的错误
+    MissingMacroName(TokenLocation), // #define、#undef 后不是宏名
+    MacroRedefined {
+        name: String,
+        location: TokenLocation,
+    }, // 宏被重新定义为不同的内容
+    InvalidParameterList(TokenLocation), // 函数式宏的参数列表不合法
+    DuplicateParameter {
+        name: String,
+        location: TokenLocation,
+    }, // 参数名重复
+    StringifyNonParameter(TokenLocation), // # 后面不是宏参数
+    PasteAtEdge(TokenLocation),      // ## 出现在替换列表的开头或结尾
+    VaArgsOutsideVariadic(TokenLocation), // __VA_ARGS__ 只能出现在可变参数宏中
+    UnterminatedArgumentList {
+        name: String,
+        location: TokenLocation,
+    }, // 宏调用的参数列表没有闭合
+    WrongArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+        location: TokenLocation,
+    }, // 宏调用的参数个数不对
+    InvalidPaste {
+        text: String,
+        location: TokenLocation,
+    }, // ## 拼接的结果不是一个合法的 token
+    UnknownDirective {
+        name: String,
+        location: TokenLocation,
+    }, // 未知的预处理指令
+    MalformedInclude(TokenLocation), // #include 后不是 "FILENAME" 或 <FILENAME>
+    IncludeNotFound {
+        name: String,
+        location: TokenLocation,
+    }, // 在搜索路径中找不到被包含的文件
+    IncludeFailed {
+        name: String,
+        reason: String,
+        location: TokenLocation,
+    }, // 找到了文件但读取失败
+    IncludeCycle {
+        name: String,
+        location: TokenLocation,
+    }, // 文件直接或间接地包含了自身
+    UnterminatedConditional {
+        directive: String,
+        location: TokenLocation,
+    }, // 文件结束时条件编译仍未闭合，location 为开始的指令
+    UnmatchedConditional {
+        directive: String,
+        location: TokenLocation,
+    }, // #elif、#else、#endif 前面没有 #if
+    DirectiveAfterElse {
+        directive: String,
+        location: TokenLocation,
+    }, // #else 之后又出现 #elif 或 #else
+    InvalidCondition {
+        message: String,
+        location: TokenLocation,
+    }, // #if 的表达式不合法
+    DivisionByZero(TokenLocation),   // #if 的表达式中除以零
+    ExtraTokens {
+        directive: String,
+        location: TokenLocation,
+    }, // 指令末尾多余的 token
+    ErrorDirective {
+        message: String,
+        location: TokenLocation,
+    }, // 生效的 #error 指令
+    WarningDirective {
+        message: String,
+        location: TokenLocation,
+    }, // 生效的 #warning 指令
+    InvalidLineDirective(TokenLocation), // #line 后不是合法的行号与文件名
+}
+
+impl PreprocessError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PreprocessError::Lex { error, .. } => error.code(),
+            PreprocessError::MissingMacroName(_) => "E0101",
+            PreprocessError::MacroRedefined { .. } => "W0102",
+            PreprocessError::InvalidParameterList(_) => "E0103",
+            PreprocessError::DuplicateParameter { .. } => "E0104",
+            PreprocessError::StringifyNonParameter(_) => "E0105",
+            PreprocessError::PasteAtEdge(_) => "E0106",
+            PreprocessError::VaArgsOutsideVariadic(_) => "W0107",
+            PreprocessError::UnterminatedArgumentList { .. } => "E0108",
+            PreprocessError::WrongArgumentCount { .. } => "E0109",
+            PreprocessError::InvalidPaste { .. } => "E0110",
+            PreprocessError::UnknownDirective { .. } => "E0111",
+            PreprocessError::MalformedInclude(_) => "E0112",
+            PreprocessError::IncludeNotFound { .. } => "E0113",
+            PreprocessError::IncludeFailed { .. } => "E0114",
+            PreprocessError::IncludeCycle { .. } => "E0115",
+            PreprocessError::UnterminatedConditional { .. } => "E0116",
+            PreprocessError::UnmatchedConditional { .. } => "E0117",
+            PreprocessError::DirectiveAfterElse { .. } => "E0118",
+            PreprocessError::InvalidCondition { .. } => "E0119",
+            PreprocessError::DivisionByZero(_) => "E0120",
+            PreprocessError::ExtraTokens { .. } => "W0121",
+            PreprocessError::ErrorDirective { .. } => "E0122",
+            PreprocessError::WarningDirective { .. } => "W0123",
+            PreprocessError::InvalidLineDirective(_) => "E0124",
+        }
+    }
+
+    pub fn location(&self) -> &TokenLocation {
+        match self {
+            PreprocessError::Lex { location, .. }
+            | PreprocessError::MacroRedefined { location, .. }
+            | PreprocessError::DuplicateParameter { location, .. }
+            | PreprocessError::UnterminatedArgumentList { location, .. }
+            | PreprocessError::WrongArgumentCount { location, .. }
+            | PreprocessError::InvalidPaste { location, .. }
+            | PreprocessError::UnknownDirective { location, .. }
+            | PreprocessError::IncludeNotFound { location, .. }
+            | PreprocessError::IncludeFailed { location, .. }
+            | PreprocessError::IncludeCycle { location, .. }
+            | PreprocessError::UnterminatedConditional { location, .. }
+            | PreprocessError::UnmatchedConditional { location, .. }
+            | PreprocessError::DirectiveAfterElse { location, .. }
+            | PreprocessError::InvalidCondition { location, .. }
+            | PreprocessError::ExtraTokens { location, .. }
+            | PreprocessError::ErrorDirective { location, .. }
+            | PreprocessError::WarningDirective { location, .. } => location,
+            PreprocessError::MissingMacroName(location)
+            | PreprocessError::InvalidParameterList(location)
+            | PreprocessError::StringifyNonParameter(location)
+            | PreprocessError::PasteAtEdge(location)
+            | PreprocessError::VaArgsOutsideVariadic(location)
+            | PreprocessError::MalformedInclude(location)
+            | PreprocessError::DivisionByZero(location)
+            | PreprocessError::InvalidLineDirective(location) => location,
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            PreprocessError::Lex { error, .. } => error.severity(),
+            PreprocessError::MacroRedefined { .. }
+            | PreprocessError::VaArgsOutsideVariadic(_)
+            | PreprocessError::ExtraTokens { .. }
+            | PreprocessError::WarningDirective { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            PreprocessError::Lex { error, .. } => return write!(f, "{}", error),
+            PreprocessError::MissingMacroName(_) => "macro names must be identifiers".to_string(),
+            PreprocessError::MacroRedefined { name, .. } => format!("\"{}\" redefined", name),
+            PreprocessError::InvalidParameterList(_) => "invalid macro parameter list".to_string(),
+            PreprocessError::DuplicateParameter { name, .. } => {
+                format!("duplicate macro parameter \"{}\"", name)
+            }
+            PreprocessError::StringifyNonParameter(_) => {
+                "'#' is not followed by a macro parameter".to_string()
+            }
+            PreprocessError::PasteAtEdge(_) => {
+                "'##' cannot appear at either end of a macro expansion".to_string()
+            }
+            PreprocessError::VaArgsOutsideVariadic(_) => {
+                "__VA_ARGS__ can only appear in the expansion of a variadic macro".to_string()
+            }
+            PreprocessError::UnterminatedArgumentList { name, .. } => {
+                format!("unterminated argument list invoking macro \"{}\"", name)
+            }
+            PreprocessError::WrongArgumentCount {
+                name,
+                expected,
+                found,
+                ..
+            } => format!(
+                "macro \"{}\" requires {} arguments, but {} given",
+                name, expected, found
+            ),
+            PreprocessError::InvalidPaste { text, .. } => format!(
+                "pasting \"{}\" does not give a valid preprocessing token",
+                text
+            ),
+            PreprocessError::UnknownDirective { name, .. } => {
+                format!("invalid preprocessing directive #{}", name)
+            }
+            PreprocessError::MalformedInclude(_) => {
+                "#include expects \"FILENAME\" or <FILENAME>".to_string()
+            }
+            PreprocessError::IncludeNotFound { name, .. } => {
+                format!("{}: No such file or directory", name)
+            }
+            PreprocessError::IncludeFailed { name, reason, .. } => {
+                format!("cannot read {}: {}", name, reason)
+            }
+            PreprocessError::IncludeCycle { name, .. } => {
+                format!("#include nested recursively: {}", name)
+            }
+            PreprocessError::UnterminatedConditional { directive, .. } => {
+                format!("unterminated #{}", directive)
+            }
+            PreprocessError::UnmatchedConditional { directive, .. } => {
+                format!("#{} without #if", directive)
+            }
+            PreprocessError::DirectiveAfterElse { directive, .. } => {
+                format!("#{} after #else", directive)
+            }
+            PreprocessError::InvalidCondition { message, .. } => message.clone(),
+            PreprocessError::DivisionByZero(_) => "division by zero in #if".to_string(),
+            PreprocessError::ExtraTokens { directive, .. } => {
+                format!("extra tokens at end of #{} directive", directive)
+            }
+            PreprocessError::ErrorDirective { message, .. } => format!("#error {}", message),
+            PreprocessError::WarningDirective { message, .. } => format!("#warning {}", message),
+            PreprocessError::InvalidLineDirective(_) => {
+                "#line expects a positive line number and an optional \"FILENAME\"".to_string()
+            }
+        };
+        write!(f, "{} [{}]", message, self.code())
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+// 由预处理器自己计算替换结果的预定义宏
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Builtin {
+    Line, // __LINE__
+    File, // __FILE__
+}
+
+#[derive(Debug, Clone)]
+enum MacroBody {
+    Tokens(Vec<PpToken>),
+    Builtin(Builtin),
+}
+
+// 宏定义，params 为 None 表示对象式宏
+#[derive(Debug, Clone)]
+struct Macro {
+    params: Option<Vec<String>>,
+    variadic: bool,
+    body: MacroBody,
+}
+
+impl Macro {
+    // 参数在实参列表中的序号，可变参数宏的 __VA_ARGS__ 排在最后
+    fn param_index(&self, token: &PpToken) -> Option<usize> {
+        let params = self.params.as_ref()?;
+        let name = token.macro_name()?;
+        if self.variadic && name == "__VA_ARGS__" {
+            return Some(params.len());
+        }
+        params.iter().position(|param| param == name)
+    }
+
+    // 按 C 标准判断两个定义是否相同，替换列表中的空白只比较有无
+    fn same_definition(&self, other: &Macro) -> bool {
+        let (MacroBody::Tokens(body), MacroBody::Tokens(other_body)) = (&self.body, &other.body)
+        else {
+            return false;
+        };
+        self.params == other.params
+            && self.variadic == other.variadic
+            && body.len() == other_body.len()
+            && body.iter().zip(other_body).enumerate().all(|(i, (a, b))| {
+                a.spelling == b.spelling && (i == 0 || a.leading_space == b.leading_space)
+            })
+    }
+}
+
+// 替换列表展开时的中间结果，Placemarker 表示空的实参，在 ## 之后删除
+enum Item {
+    Token(PpToken),
+    Placemarker,
+}
+
+// 参与预处理的文件，文件编号即在 files 中的下标
+struct SourceFile {
+    name: String,                         // 用于诊断与 __FILE__ 的名字
+    path: PathBuf,                        // 规范化之后的路径，用于判断是否为同一文件
+    included_from: Option<TokenLocation>, // 包含该文件的 #include 所在位置
+    search_index: Option<usize>,          // 在搜索路径中被找到的位置，用于 #include_next
+    line_offset: i64,                     // #line 指定的行号与实际行号之差
+    presumed_name: Option<String>,        // #line 指定的文件名，用于 __FILE__
+}
+
+// 正在处理的文件，包含关系构成一个栈
+struct Frame {
+    file_id: usize,
+    tokens: VecDeque<Result<PpToken, PreprocessError>>, // 尚未处理的 token，词法错误按位置夹在其中
+    conditionals: Vec<Conditional>,                     // 尚未闭合的条件编译
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Branch {
+    Active,  // 正在处理的分支
+    Waiting, // 还没有条件成立的分支，跳过当前分支
+    Done,    // 已经处理过一个分支，或者整个条件位于被跳过的部分中
+}
+
+// 部分预处理中的一组 #if ... #endif
+// kept 为 true 时条件未知，指令保留在输出中，此时 Waiting 表示删除条件为假的 #elif 分支
+struct PartialGroup {
+    branch: Branch,
+    kept: bool,
+    closed: bool, // 保留的组中有 #elif 的条件为真并改写成了 #else
+    has_else: bool,
+    directive: String,
+    location: TokenLocation,
+}
+
+// 部分预处理时指令行的输出方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Emit {
+    Keep,
+    Drop,
+    Rename(&'static str), // 改写指令名，例如前面分支都被删除的 #elif 改为 #if
+}
+
+// 一组 #if ... #endif，location 为开始的指令
+struct Conditional {
+    branch: Branch,
+    has_else: bool,
+    directive: String,
+    location: TokenLocation,
+}
+
+pub struct Preprocessor {
+    macros: HashMap<String, Rc<Macro>>,
+    files: Vec<SourceFile>,
+    frames: Vec<Frame>,
+    pending: VecDeque<PpToken>, // 宏展开后等待重新扫描的 token
+    errors: Vec<PreprocessError>,
+    standard: LanguageStandard,
+    language: Language,
+    trigraphs: bool,
+    include_dirs: Vec<PathBuf>,       // -I 指定的目录
+    system_dirs: Vec<PathBuf>,        // -isystem 指定的目录，排在 -I 之后
+    once_files: HashSet<PathBuf>,     // 使用了 #pragma once 的文件
+    guards: HashMap<PathBuf, String>, // 整个文件被 #ifndef 保护时对应的宏名
+    known: HashSet<String>,           // 通过 define、undefine 明确给出的宏
+    partial: bool,                    // 部分预处理，只有 known 中的宏视为已知
+}
+
+impl Default for Preprocessor {
+    fn default() -> Self {
+        Preprocessor::new()
+    }
+}
+
+impl Preprocessor {
+    pub fn new() -> Self {
+        let mut preprocessor = Preprocessor {
+            macros: HashMap::new(),
+            files: Vec::new(),
+            frames: Vec::new(),
+            pending: VecDeque::new(),
+            errors: Vec::new(),
+            standard: LanguageStandard::default(),
+            language: Language::default(),
+            trigraphs: false,
+            include_dirs: Vec::new(),
+            system_dirs: Vec::new(),
+            once_files: HashSet::new(),
+            guards: HashMap::new(),
+            known: HashSet::new(),
+            partial: false,
+        };
+        preprocessor.define_builtins();
+        preprocessor
+    }
+
+    // 设置语言标准，同时决定 __STDC_VERSION__ 的值
+    pub fn with_standard(mut self, standard: LanguageStandard) -> Self {
+        self.standard = standard;
+        self.define_builtins();
+        self
+    }
+
+    // 设置源码的语言，C++ 时定义 __cplusplus 而不定义 __STDC_VERSION__
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = language;
+        self.define_builtins();
+        self
+    }
+
+    // 相当于命令行的 -trigraphs，预处理前替换源文件中的三字符组
+    pub fn with_trigraphs(mut self, enabled: bool) -> Self {
+        self.trigraphs = enabled;
+        self
+    }
+
+    // 相当于命令行的 -I，#include "..." 与 #include <...> 都会搜索该目录
+    pub fn with_include_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.include_dirs.push(dir.into());
+        self
+    }
+
+    // 相当于命令行的 -isystem，在 -I 的目录之后搜索
+    pub fn with_system_include_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.system_dirs.push(dir.into());
+        self
+    }
+
+    // 相当于命令行的 -D，name 可以带参数列表如 F(x)，与 #define 一样检查，返回定义中的错误
+    pub fn define(&mut self, name: &str, value: &str) -> Vec<PreprocessError> {
+        let errors = self.define_text(name, value);
+        // 定义失败的宏仍是未知的
+        if !errors
+            .iter()
+            .any(|error| error.severity() == Severity::Error)
+        {
+            let name = name.split('(').next().unwrap_or(name);
+            self.known.insert(name.trim().to_string());
+        }
+        errors
+    }
+
+    // 相当于命令行的 -U
+    pub fn undefine(&mut self, name: &str) {
+        self.known.insert(name.to_string());
+        self.macros.remove(name);
+    }
+
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.macros.contains_key(name)
+    }
+
+    // 定义预定义宏，预定义宏不算作 define 明确给出的宏，改变语言标准时直接替换
+    fn define_object(&mut self, name: &str, value: &str) {
+        self.macros.remove(name);
+        self.define_text(name, value);
+    }
+
+    // 把 name 与 value 当作一条 #define 指令处理，返回定义过程中产生的错误
+    fn define_text(&mut self, name: &str, value: &str) -> Vec<PreprocessError> {
+        let start = self.errors.len();
+        match self.lex_text(&format!("define {} {}", name, value), None) {
+            Ok(line) => self.define_directive(&line),
+            Err(error) => self.errors.push(error),
+        }
+        self.errors.split_off(start)
+    }
+
+    fn define_builtins(&mut self) {
+        let builtins = [("__LINE__", Builtin::Line), ("__FILE__", Builtin::File)];
+        for (name, builtin) in builtins {
+            self.macros.insert(
+                name.to_string(),
+                Rc::new(Macro {
+                    params: None,
+                    variadic: false,
+                    body: MacroBody::Builtin(builtin),
+                }),
+            );
+        }
+        let (date, time) = build_date_time();
+        self.define_object("__DATE__", &date);
+        self.define_object("__TIME__", &time);
+        self.define_object("__STDC__", "1");
+        self.define_object("__STDC_HOSTED__", "1");
+        if self.language == Language::Cpp {
+            self.define_object("__cplusplus", "202002L");
+            self.macros.remove("__STDC_VERSION__");
+            return;
+        }
+        self.macros.remove("__cplusplus");
+        let version = match self.standard {
+            LanguageStandard::C89 => None,
+            LanguageStandard::C99 => Some("199901L"),
+            LanguageStandard::C11 => Some("201112L"),
+            LanguageStandard::C17 => Some("201710L"),
+            LanguageStandard::C23 => Some("202311L"),
+        };
+        match version {
+            Some(version) => self.define_object("__STDC_VERSION__", version),
+            None => {
+                self.macros.remove("__STDC_VERSION__");
+            }
+        }
+    }
+
+    // 预处理一段源码，name 用于 __FILE__，同时作为查找 #include "..." 的相对路径
+    pub fn preprocess(&mut self, name: &str, source: &str) -> (Vec<PpToken>, Vec<PreprocessError>) {
+        let file_id = self.add_file(name.to_string(), Path::new(name), None, None);
+        self.push_frame(file_id, source);
+        self.run()
+    }
+
+    // 预处理磁盘上的文件
+    pub fn preprocess_file(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> io::Result<(Vec<PpToken>, Vec<PreprocessError>)> {
+        let path = path.as_ref();
+        let source = read_source(path)?;
+        let file_id = self.add_file(path.display().to_string(), path, None, None);
+        self.push_frame(file_id, &source);
+        Ok(self.run())
+    }
+
+    // 部分预处理：只处理条件完全由 define、undefine 明确给出的宏决定的条件编译，
+    // 其余指令与保留部分的源码原样输出
+    pub fn preprocess_partial(
+        &mut self,
+        name: &str,
+        source: &str,
+    ) -> (String, Vec<PreprocessError>) {
+        let file_id = self.add_file(name.to_string(), Path::new(name), None, None);
+        let tokens = self.lex_file(source, file_id);
+        self.partial = true;
+        let output = self.run_partial(source, tokens);
+        self.partial = false;
+        (output, mem::take(&mut self.errors))
+    }
+
+    // 对磁盘上的文件做部分预处理
+    pub fn preprocess_partial_file(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> io::Result<(String, Vec<PreprocessError>)> {
+        let path = path.as_ref();
+        let source = read_source(path)?;
+        Ok(self.preprocess_partial(&path.display().to_string(), &source))
+    }
+
+    pub fn file_name(&self, file_id: usize) -> Option<&str> {
+        self.files.get(file_id).map(|file| file.name.as_str())
+    }
+
+    // 位置所在文件的包含链，由内向外依次为各层 #include 的位置
+    pub fn include_chain(&self, location: &TokenLocation) -> Vec<TokenLocation> {
+        let mut chain = Vec::new();
+        let mut file_id = location.span().file_id;
+        while let Some(parent) = self
+            .files
+            .get(file_id)
+            .and_then(|file| file.included_from.as_ref())
+        {
+            chain.push(parent.clone());
+            file_id = parent.span().file_id;
+        }
+        chain
+    }
+
+    // 按 文件:行:列 的格式输出诊断，出错位置在头文件中时先列出包含链
+    pub fn format_diagnostic(&self, error: &PreprocessError) -> String {
+        let mut text = String::new();
+        let position = |location: &TokenLocation| {
+            let name = self
+                .file_name(location.span().file_id)
+                .unwrap_or("<built-in>");
+            format!("{}:{}:{}", name, location.row(), location.col())
+        };
+        for (i, parent) in self.include_chain(error.location()).iter().enumerate() {
+            let prefix = if i == 0 {
+                "In file included from"
+            } else {
+                "                 from"
+            };
+            text.push_str(&format!("{} {},\n", prefix, position(parent)));
+        }
+        if text.ends_with(",\n") {
+            text.truncate(text.len() - 2);
+            text.push_str(":\n");
+        }
+        let severity = match error.severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        text.push_str(&format!(
+            "{}: {}: {}",
+            position(error.location()),
+            severity,
+            error
+        ));
+        text
+    }
+
+    fn add_file(
+        &mut self,
+        name: String,
+        path: &Path,
+        included_from: Option<TokenLocation>,
+        search_index: Option<usize>,
+    ) -> usize {
+        self.files.push(SourceFile {
+            name,
+            path: fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+            included_from,
+            search_index,
+            line_offset: 0,
+            presumed_name: None,
+        });
+        self.files.len() - 1
+    }
+
+    fn push_frame(&mut self, file_id: usize, source: &str) {
+        let tokens = self.lex_file(source, file_id);
+        if let Some(guard) = include_guard(&tokens) {
+            self.guards.insert(self.files[file_id].path.clone(), guard);
+        }
+        self.frames.push(Frame {
+            file_id,
+            tokens,
+            conditionals: Vec::new(),
+        });
+    }
+
+    fn run(&mut self) -> (Vec<PpToken>, Vec<PreprocessError>) {
+        let mut tokens = Vec::new();
+        loop {
+            let mut queue = mem::take(&mut self.pending);
+            let token = self.expand_next(&mut queue, true);
+            self.pending = queue;
+            let Some(token) = token else {
+                break;
+            };
+            let eof = token.token == Token::EOF;
+            tokens.push(token);
+            if eof {
+                break;
+            }
+        }
+        (tokens, mem::take(&mut self.errors))
+    }
+
+    // 按指令所在的行切分源码，被删除的分支与已确定的条件编译指令不输出，其余按原文复制
+    fn run_partial(
+        &mut self,
+        source: &str,
+        tokens: VecDeque<Result<PpToken, PreprocessError>>,
+    ) -> String {
+        let mut output = String::new();
+        let mut groups: Vec<PartialGroup> = Vec::new();
+        let mut copied = 0; // 源码中已经处理到的位置
+        let mut previous_end = 0; // 上一个 token 的结尾
+        let mut tokens = tokens.into_iter();
+        while let Some(item) = tokens.next() {
+            let skipping = groups
+                .last()
+                .is_some_and(|group| group.branch != Branch::Active);
+            let hash = match item {
+                Ok(token) if token.token == Token::Directive => token,
+                Ok(token) => {
+                    previous_end = token.location.span().end;
+                    continue;
+                }
+                Err(error) => {
+                    if !skipping {
+                        self.errors.push(error);
+                    }
+                    continue;
+                }
+            };
+            let mut line = Vec::new();
+            let mut end = source.len();
+            for item in tokens.by_ref() {
+                match item {
+                    Ok(token) if token.token == Token::EndOfDirective => {
+                        end = token.location.span().end;
+                        break;
+                    }
+                    Ok(token) => line.push(token),
+                    Err(error) if !skipping => self.errors.push(error),
+                    Err(_) => {}
+                }
+            }
+            // 指令行包括结尾的换行，开头取 # 之前最后一个注释或换行之后
+            let gap = &source[previous_end..hash.location.span().start];
+            let start = previous_end + directive_start(gap);
+            previous_end = end;
+            if !skipping {
+                output.push_str(&source[copied..start]);
+            }
+            copied = end;
+            let Some(name) = line.first() else {
+                if !skipping {
+                    output.push_str(&source[start..end]);
+                }
+                continue;
+            };
+            match self.partial_directive(&line, &mut groups, skipping) {
+                Emit::Keep => output.push_str(&source[start..end]),
+                Emit::Drop => {}
+                // 只替换指令名，行内其余内容保持不变
+                Emit::Rename(directive) => {
+                    let span = name.location.span();
+                    output.push_str(&source[start..span.start]);
+                    output.push_str(directive);
+                    if directive != "else" {
+                        output.push_str(&source[span.end..end]);
+                    } else if let Some(newline) = ["\r\n", "\n"]
+                        .into_iter()
+                        .find(|newline| source[start..end].ends_with(newline))
+                    {
+                        // #else 后面不能有条件，去掉原来 #elif 的表达式，保留原来的换行符
+                        output.push_str(newline);
+                    }
+                }
+            }
+        }
+        let skipping = groups
+            .last()
+            .is_some_and(|group| group.branch != Branch::Active);
+        if !skipping {
+            output.push_str(&source[copied..]);
+        }
+        for group in groups {
+            self.errors.push(PreprocessError::UnterminatedConditional {
+                directive: group.directive,
+                location: group.location,
+            });
+        }
+        output
+    }
+
+    fn partial_directive(
+        &mut self,
+        line: &[PpToken],
+        groups: &mut Vec<PartialGroup>,
+        skipping: bool,
+    ) -> Emit {
+        let directive = &line[0];
+        let unchanged = if skipping { Emit::Drop } else { Emit::Keep };
+        match directive.spelling.as_str() {
+            "if" | "ifdef" | "ifndef" => {
+                let (branch, emit) = match skipping {
+                    true => (Branch::Done, Emit::Drop),
+                    false => match self.condition(line) {
+                        Some(true) => (Branch::Active, Emit::Drop),
+                        Some(false) => (Branch::Waiting, Emit::Drop),
+                        None => (Branch::Active, Emit::Keep),
+                    },
+                };
+                groups.push(PartialGroup {
+                    branch,
+                    kept: emit == Emit::Keep,
+                    closed: false,
+                    has_else: false,
+                    directive: directive.spelling.clone(),
+                    location: directive.location.clone(),
+                });
+                emit
+            }
+            "elif" | "elifdef" | "elifndef" | "else" => {
+                let Some(group) = groups.last_mut() else {
+                    self.errors.push(PreprocessError::UnmatchedConditional {
+                        directive: directive.spelling.clone(),
+                        location: directive.location.clone(),
+                    });
+                    return unchanged;
+                };
+                if group.has_else {
+                    self.errors.push(PreprocessError::DirectiveAfterElse {
+                        directive: directive.spelling.clone(),
+                        location: directive.location.clone(),
+                    });
+                    return unchanged;
+                }
+                let is_else = directive.spelling == "else";
+                group.has_else = is_else;
+                let (kept, closed, branch) = (group.kept, group.closed, group.branch);
+                let (branch, emit) = match (kept, branch) {
+                    // 某个 #elif 已改写为 #else，之后的分支全部删除
+                    (true, _) if closed => (Branch::Done, Emit::Drop),
+                    (true, _) if is_else => (Branch::Active, Emit::Keep),
+                    (true, _) => match self.condition(line) {
+                        Some(true) => (Branch::Active, Emit::Rename("else")),
+                        Some(false) => (Branch::Waiting, Emit::Drop),
+                        None => (Branch::Active, Emit::Keep),
+                    },
+                    (false, Branch::Active | Branch::Done) => (Branch::Done, Emit::Drop),
+                    (false, Branch::Waiting) if is_else => (Branch::Active, Emit::Drop),
+                    // 前面的分支都已删除，条件未知的 #elif 成为新的 #if
+                    (false, Branch::Waiting) => match self.condition(line) {
+                        Some(true) => (Branch::Active, Emit::Drop),
+                        Some(false) => (Branch::Waiting, Emit::Drop),
+                        None => {
+                            let renamed = match directive.spelling.as_str() {
+                                "elifdef" => "ifdef",
+                                "elifndef" => "ifndef",
+                                _ => "if",
+                            };
+                            (Branch::Active, Emit::Rename(renamed))
+                        }
+                    },
+                };
+                if let Some(group) = groups.last_mut() {
+                    group.closed |= emit == Emit::Rename("else");
+                    group.kept |= emit != Emit::Drop;
+                    group.branch = branch;
+                }
+                emit
+            }
+            "endif" => match groups.pop() {
+                Some(group) if group.kept => Emit::Keep,
+                Some(_) => Emit::Drop,
+                None => {
+                    self.errors.push(PreprocessError::UnmatchedConditional {
+                        directive: directive.spelling.clone(),
+                        location: directive.location.clone(),
+                    });
+                    unchanged
+                }
+            },
+            _ => unchanged,
+        }
+    }
+
+    // 将整个文件切分为 token，记录每个 token 的原始文本与前导空白
+    // 词法错误暂不报告，留到确定所在的部分没有被条件编译跳过时再报告
+    fn lex_file(
+        &mut self,
+        source: &str,
+        file_id: usize,
+    ) -> VecDeque<Result<PpToken, PreprocessError>> {
+        let mut lexer = Lexer::new(source)
+            .with_file_id(file_id)
+            .with_standard(self.standard)
+            .with_language(self.language)
+            .with_trigraphs(self.trigraphs);
+        let mut tokens = VecDeque::new();
+        let mut previous_end = 0;
+        while let Some(item) = lexer.next_token() {
+            match item {
+                Ok((token, span)) => {
+                    tokens.push_back(Ok(PpToken {
+                        token: token.into_owned(),
+                        location: lexer.location(span),
+                        expanded_from: None,
+                        spelling: spelling(span.source_text(source), self.trigraphs).into_owned(),
+                        // 只隔着续行的两个 token 之间没有空白
+                        leading_space: !spelling(&source[previous_end..span.start], false)
+                            .is_empty(),
+                        hide_set: Rc::default(),
+                    }));
+                    previous_end = span.end;
+                }
+                Err(error) => {
+                    let location = lexer.location(error.span());
+                    tokens.push_back(Err(PreprocessError::Lex { error, location }));
+                }
+            }
+        }
+        tokens
+    }
+
+    // 将一段文本重新切分为 token，位置全部取自 origin；出现词法错误时返回该错误
+    fn lex_text(
+        &self,
+        text: &str,
+        origin: Option<&PpToken>,
+    ) -> Result<Vec<PpToken>, PreprocessError> {
+        let mut lexer = Lexer::new(text)
+            .with_file_id(BUILTIN_FILE)
+            .with_standard(self.standard)
+            .with_language(self.language);
+        let mut tokens = Vec::new();
+        let mut previous_end = 0;
+        while let Some(item) = lexer.next_token() {
+            let (token, span) = item.map_err(|error| PreprocessError::Lex {
+                location: match origin {
+                    Some(origin) => origin.location.clone(),
+                    None => lexer.location(error.span()),
+                },
+                error,
+            })?;
+            if token == Token::EOF {
+                break;
+            }
+            let (location, expanded_from) = match origin {
+                Some(origin) => (origin.location.clone(), origin.expanded_from.clone()),
+                None => (lexer.location(span), None),
+            };
+            tokens.push(PpToken {
+                token: token.into_owned(),
+                location,
+                expanded_from,
+                spelling: span.source_text(text).to_string(),
+                leading_space: span.start > previous_end,
+                hide_set: Rc::default(),
+            });
+            previous_end = span.end;
+        }
+        Ok(tokens)
+    }
+
+    // 从当前文件取下一个 token，遇到预处理指令时先处理指令，头文件结束后回到包含它的文件
+    fn next_file_token(&mut self) -> Option<PpToken> {
+        loop {
+            let frame = self.frames.last_mut()?;
+            let Some(item) = frame.tokens.pop_front() else {
+                self.frames.pop();
+                continue;
+            };
+            let token = match item {
+                Ok(token) => token,
+                Err(error) => {
+                    // 被跳过的部分不报告词法错误
+                    if !self.skipping() {
+                        self.errors.push(error);
+                    }
+                    continue;
+                }
+            };
+            if token.token == Token::EOF {
+                self.close_conditionals();
+                if self.frames.len() > 1 {
+                    self.frames.pop();
+                    continue;
+                }
+                return Some(token);
+            }
+            if token.token == Token::Directive {
+                self.directive();
+                continue;
+            }
+            if self.skipping() {
+                continue;
+            }
+            return Some(token);
+        }
+    }
+
+    // 当前是否位于被条件编译跳过的部分
+    fn skipping(&self) -> bool {
+        self.frames
+            .last()
+            .and_then(|frame| frame.conditionals.last())
+            .is_some_and(|conditional| conditional.branch != Branch::Active)
+    }
+
+    // 文件结束时报告所有没有闭合的条件编译
+    fn close_conditionals(&mut self) {
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        for conditional in frame.conditionals.drain(..) {
+            self.errors.push(PreprocessError::UnterminatedConditional {
+                directive: conditional.directive,
+                location: conditional.location,
+            });
+        }
+    }
+
+    // 读取指令行中剩余的 token，不含结尾的 EndOfDirective
+    fn directive_line(&mut self) -> Vec<PpToken> {
+        let skipping = self.skipping();
+        let mut line = Vec::new();
+        let Some(frame) = self.frames.last_mut() else {
+            return line;
+        };
+        while let Some(item) = frame.tokens.pop_front() {
+            match item {
+                Ok(token) if token.token == Token::EndOfDirective => break,
+                Ok(token) => line.push(token),
+                Err(error) if !skipping => self.errors.push(error),
+                Err(_) => {}
+            }
+        }
+        line
+    }
+
+    fn directive(&mut self) {
+        let skipping = self.skipping();
+        let line = self.directive_line();
+        let Some(name) = line.first() else {
+            return; // 空指令 #
+        };
+        match name.spelling.as_str() {
+            "if" | "ifdef" | "ifndef" => self.if_directive(&line, skipping),
+            "elif" | "elifdef" | "elifndef" | "else" => self.else_directive(&line),
+            "endif" => self.endif_directive(&line),
+            // 被跳过的部分只处理条件编译指令
+            _ if skipping => {}
+            "define" => self.define_directive(&line),
+            "undef" => match line.get(1).and_then(|token| token.macro_name()) {
+                Some(name) => {
+                    self.macros.remove(name);
+                }
+                None => {
+                    let location = line.get(1).unwrap_or(&line[0]).location.clone();
+                    self.errors
+                        .push(PreprocessError::MissingMacroName(location));
+                }
+            },
+            "include" | "include_next" | "import" => self.include_directive(&line),
+            "pragma" => {
+                if line.get(1).is_some_and(|token| token.spelling == "once") {
+                    let path = self.files[line[0].location.span().file_id].path.clone();
+                    self.once_files.insert(path);
+                }
+            }
+            "error" | "warning" => self.diagnostic_directive(&line),
+            "line" => self.line_directive(&line),
+            // #ident 只向目标文件写入注释，预处理时忽略
+            "ident" => {}
+            _ => self.errors.push(PreprocessError::UnknownDirective {
+                name: name.spelling.clone(),
+                location: name.location.clone(),
+            }),
+        }
+    }
+
+    fn if_directive(&mut self, line: &[PpToken], skipping: bool) {
+        let branch = if skipping {
+            Branch::Done
+        } else if self.condition(line) == Some(true) {
+            Branch::Active
+        } else {
+            Branch::Waiting
+        };
+        if let Some(frame) = self.frames.last_mut() {
+            frame.conditionals.push(Conditional {
+                branch,
+                has_else: false,
+                directive: line[0].spelling.clone(),
+                location: line[0].location.clone(),
+            });
+        }
+    }
+
+    fn else_directive(&mut self, line: &[PpToken]) {
+        let directive = &line[0];
+        let Some(&Conditional {
+            branch, has_else, ..
+        }) = self
+            .frames
+            .last()
+            .and_then(|frame| frame.conditionals.last())
+        else {
+            self.errors.push(PreprocessError::UnmatchedConditional {
+                directive: directive.spelling.clone(),
+                location: directive.location.clone(),
+            });
+            return;
+        };
+        if has_else {
+            self.errors.push(PreprocessError::DirectiveAfterElse {
+                directive: directive.spelling.clone(),
+                location: directive.location.clone(),
+            });
+            return;
+        }
+        let is_else = directive.spelling == "else";
+        let branch = match branch {
+            Branch::Active | Branch::Done => Branch::Done,
+            Branch::Waiting if is_else => Branch::Active,
+            // 只有前面的分支都不成立时才计算 #elif 的条件
+            Branch::Waiting if self.condition(line) == Some(true) => Branch::Active,
+            Branch::Waiting => Branch::Waiting,
+        };
+        if is_else {
+            self.extra_tokens(line, 1);
+        }
+        if let Some(conditional) = self
+            .frames
+            .last_mut()
+            .and_then(|frame| frame.conditionals.last_mut())
+        {
+            conditional.branch = branch;
+            conditional.has_else = is_else;
+        }
+    }
+
+    fn endif_directive(&mut self, line: &[PpToken]) {
+        let popped = self
+            .frames
+            .last_mut()
+            .and_then(|frame| frame.conditionals.pop());
+        if popped.is_none() {
+            self.errors.push(PreprocessError::UnmatchedConditional {
+                directive: line[0].spelling.clone(),
+                location: line[0].location.clone(),
+            });
+            return;
+        }
+        self.extra_tokens(line, 1);
+    }
+
+    fn extra_tokens(&mut self, line: &[PpToken], expected: usize) {
+        if let Some(extra) = line.get(expected) {
+            self.errors.push(PreprocessError::ExtraTokens {
+                directive: line[0].spelling.clone(),
+                location: extra.location.clone(),
+            });
+        }
+    }
+
+    // 计算 #if、#ifdef、#elif 等指令的条件，表达式有误或部分预处理时依赖未知的宏则为 None
+    fn condition(&mut self, line: &[PpToken]) -> Option<bool> {
+        let directive = &line[0];
+        let negate = match directive.spelling.as_str() {
+            "ifdef" | "elifdef" => false,
+            "ifndef" | "elifndef" => true,
+            _ => return self.evaluate(line),
+        };
+        let Some(name) = line.get(1).and_then(|token| token.macro_name()) else {
+            let location = line.get(1).unwrap_or(directive).location.clone();
+            self.errors
+                .push(PreprocessError::MissingMacroName(location));
+            return None;
+        };
+        self.extra_tokens(line, 2);
+        if !self.is_known(name) {
+            return None;
+        }
+        Some(self.macros.contains_key(name) != negate)
+    }
+
+    // 部分预处理时只有 define、undefine 明确给出的宏是已知的
+    fn is_known(&self, name: &str) -> bool {
+        !self.partial || self.known.contains(name)
+    }
+
+    // 先替换 defined X 与 defined(X)，再展开宏，最后计算整数常量表达式
+    fn evaluate(&mut self, line: &[PpToken]) -> Option<bool> {
+        let directive = &line[0];
+        let mut tokens = Vec::new();
+        let mut i = 1;
+        while i < line.len() {
+            let token = &line[i];
+            if token.macro_name() != Some("defined") {
+                let mut token = token.clone();
+                // 未知的宏不展开，留给表达式作为未知的值
+                if let Some(name) = token.macro_name().filter(|name| !self.is_known(name)) {
+                    let mut hide_set = (*token.hide_set).clone();
+                    hide_set.insert(name.to_string());
+                    token.hide_set = Rc::new(hide_set);
+                }
+                tokens.push(token);
+                i += 1;
+                continue;
+            }
+            let parenthesized = line
+                .get(i + 1)
+                .is_some_and(|next| next.is_delimiter(Delimiter::LeftParenthesis));
+            let name_index = if parenthesized { i + 2 } else { i + 1 };
+            let name = line.get(name_index).and_then(|name| name.macro_name());
+            let closed = !parenthesized
+                || line
+                    .get(name_index + 1)
+                    .is_some_and(|next| next.is_delimiter(Delimiter::RightParenthesis));
+            let (Some(name), true) = (name, closed) else {
+                self.errors.push(PreprocessError::InvalidCondition {
+                    message: "operator \"defined\" requires an identifier".to_string(),
+                    location: token.location.clone(),
+                });
+                return None;
+            };
+            // 未知的 defined X 整体换成 defined，表达式把它当作未知的值
+            if self.is_known(name) {
+                let value = u64::from(self.macros.contains_key(name));
+                tokens.push(integer_token(token, value));
+            } else {
+                tokens.push(token.clone());
+            }
+            i = name_index + if parenthesized { 2 } else { 1 };
+        }
+        let tokens = self.expand_list(tokens);
+        if tokens.is_empty() {
+            self.errors.push(PreprocessError::InvalidCondition {
+                message: format!("#{} with no expression", directive.spelling),
+                location: directive.location.clone(),
+            });
+            return None;
+        }
+        let mut parser = ConditionParser {
+            tokens: &tokens,
+            position: 0,
+            directive,
+            known: self.partial.then_some(&self.known),
+            boolean_literals: self.standard >= LanguageStandard::C23
+                || self.language == Language::Cpp,
+        };
+        match parser.parse() {
+            Ok(value) => value.known.then_some(value.bits != 0),
+            Err(error) => {
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    // #error、#warning 报告指令行中剩余的原文
+    fn diagnostic_directive(&mut self, line: &[PpToken]) {
+        let directive = &line[0];
+        let mut message = String::new();
+        for (i, token) in line[1..].iter().enumerate() {
+            if i > 0 && token.leading_space {
+                message.push(' ');
+            }
+            message.push_str(&token.spelling);
+        }
+        let location = directive.location.clone();
+        self.errors.push(match directive.spelling.as_str() {
+            "error" => PreprocessError::ErrorDirective { message, location },
+            _ => PreprocessError::WarningDirective { message, location },
+        });
+    }
+
+    // #line N ["FILENAME"] 改变之后各行的 __LINE__ 与 __FILE__
+    fn line_directive(&mut self, line: &[PpToken]) {
+        let directive = &line[0];
+        let tokens = self.expand_list(line[1..].to_vec());
+        // 行号只能由十进制数字组成
+        let number = tokens.first().and_then(|token| match &token.token {
+            Token::Numbers(Number::Integer(constant))
+                if token.spelling.bytes().all(|c| c.is_ascii_digit()) =>
+            {
+                Some(constant.value)
+            }
+            _ => None,
+        });
+        let name = match tokens.get(1).map(|token| &token.token) {
+            None => Some(None),
+            Some(Token::Strings(literal)) if literal.encoding == Encoding::Plain => {
+                let bytes: Vec<u8> = literal.value.iter().map(|&c| c as u8).collect();
+                Some(Some(String::from_utf8_lossy(&bytes).into_owned()))
+            }
+            Some(_) => None,
+        };
+        let (Some(number @ 1..=2147483647), Some(name), None) = (number, name, tokens.get(2))
+        else {
+            let location = tokens.first().unwrap_or(directive).location.clone();
+            self.errors
+                .push(PreprocessError::InvalidLineDirective(location));
+            return;
+        };
+        // 指令的下一行的行号为 number
+        let row = line.last().unwrap_or(directive).location.row() as i64 + 1;
+        let file = &mut self.files[directive.location.span().file_id];
+        file.line_offset = number as i64 - row;
+        if name.is_some() {
+            file.presumed_name = name;
+        }
+    }
+
+    fn include_directive(&mut self, line: &[PpToken]) {
+        let directive = &line[0];
+        let Some((name, angled)) = self.include_name(line) else {
+            let location = line.get(1).unwrap_or(directive).location.clone();
+            self.errors
+                .push(PreprocessError::MalformedInclude(location));
+            return;
+        };
+        let current = &self.files[directive.location.span().file_id];
+        let next = directive.spelling == "include_next";
+        let Some((path, search_index)) = self.resolve_include(&name, angled, next, current) else {
+            self.errors.push(PreprocessError::IncludeNotFound {
+                name,
+                location: directive.location.clone(),
+            });
+            return;
+        };
+        let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if directive.spelling == "import" {
+            self.once_files.insert(canonical.clone());
+        }
+        // #pragma once 的文件或者保护宏已定义的文件不需要再次读取
+        if self.once_files.contains(&canonical)
+            || self
+                .guards
+                .get(&canonical)
+                .is_some_and(|guard| self.macros.contains_key(guard))
+        {
+            return;
+        }
+        if self
+            .frames
+            .iter()
+            .any(|frame| self.files[frame.file_id].path == canonical)
+        {
+            self.errors.push(PreprocessError::IncludeCycle {
+                name,
+                location: directive.location.clone(),
+            });
+            return;
+        }
+        let source = match read_source(&path) {
+            Ok(source) => source,
+            Err(error) => {
+                self.errors.push(PreprocessError::IncludeFailed {
+                    name,
+                    reason: error.to_string(),
+                    location: directive.location.clone(),
+                });
+                return;
+            }
+        };
+        let file_id = self.add_file(
+            path.display().to_string(),
+            &path,
+            Some(directive.location.clone()),
+            search_index,
+        );
+        self.push_frame(file_id, &source);
+    }
+
+    // 取出被包含的文件名，以及是否为 <...> 形式；不是头文件名时先展开宏
+    fn include_name(&mut self, line: &[PpToken]) -> Option<(String, bool)> {
+        if let Some(Token::HeaderName(name)) = line.get(1).map(|token| &token.token) {
+            let angled = name.starts_with('<');
+            return Some((name[1..name.len() - 1].to_string(), angled));
+        }
+        let tokens = self.expand_list(line[1..].to_vec());
+        let first = tokens.first()?;
+        if let Token::Strings(literal) = &first.token {
+            let raw = &literal.raw;
+            return (tokens.len() == 1 && literal.encoding == Encoding::Plain)
+                .then(|| (raw[1..raw.len() - 1].to_string(), false));
+        }
+        // <...> 由多个 token 组成时按原文拼接
+        if first.spelling != "<" || tokens.last()?.spelling != ">" || tokens.len() < 3 {
+            return None;
+        }
+        let mut name = String::new();
+        for (i, token) in tokens[1..tokens.len() - 1].iter().enumerate() {
+            if i > 0 && token.leading_space {
+                name.push(' ');
+            }
+            name.push_str(&token.spelling);
+        }
+        Some((name, true))
+    }
+
+    // 按照 当前文件所在目录(仅 "...")、-I、-isystem 的顺序查找文件，返回路径与搜索位置
+    fn resolve_include(
+        &self,
+        name: &str,
+        angled: bool,
+        next: bool,
+        current: &SourceFile,
+    ) -> Option<(PathBuf, Option<usize>)> {
+        let path = Path::new(name);
+        if path.is_absolute() {
+            return path.is_file().then(|| (path.to_path_buf(), None));
+        }
+        // #include_next 从找到当前文件的目录之后开始搜索
+        let (first, search_current) = match (next, current.search_index) {
+            (true, Some(index)) => (index + 1, false),
+            _ => (0, !angled),
+        };
+        if search_current {
+            let dir = current.path.parent().unwrap_or(Path::new(""));
+            let candidate = dir.join(path);
+            if candidate.is_file() {
+                return Some((candidate, None));
+            }
+        }
+        self.include_dirs
+            .iter()
+            .chain(&self.system_dirs)
+            .enumerate()
+            .skip(first)
+            .map(|(index, dir)| (dir.join(path), Some(index)))
+            .find(|(candidate, _)| candidate.is_file())
+    }
+
+    fn define_directive(&mut self, line: &[PpToken]) {
+        let Some(name) = line.get(1).and_then(|token| token.macro_name()) else {
+            let location = line.get(1).unwrap_or(&line[0]).location.clone();
+            self.errors
+                .push(PreprocessError::MissingMacroName(location));
+            return;
+        };
+        let name = name.to_string();
+        let mut index = 2;
+        let mut params = None;
+        let mut variadic = false;
+        // 宏名后紧跟 ( 才是函数式宏
+        if let Some(token) = line.get(index) {
+            if token.is_delimiter(Delimiter::LeftParenthesis) && !token.leading_space {
+                let Some((names, is_variadic, end)) = self.parameter_list(line, index + 1) else {
+                    return;
+                };
+                params = Some(names);
+                variadic = is_variadic;
+                index = end;
+            }
+        }
+        let mut body: Vec<PpToken> = line[index..].to_vec();
+        if let Some(first) = body.first_mut() {
+            first.leading_space = false;
+        }
+        let definition = Macro {
+            params,
+            variadic,
+            body: MacroBody::Tokens(Vec::new()),
+        };
+        if !self.check_body(&definition, &body) {
+            return;
+        }
+        let definition = Macro {
+            body: MacroBody::Tokens(mem::take(&mut body)),
+            ..definition
+        };
+        if let Some(previous) = self.macros.get(&name) {
+            if !previous.same_definition(&definition) {
+                self.errors.push(PreprocessError::MacroRedefined {
+                    name: name.clone(),
+                    location: line[1].location.clone(),
+                });
+            }
+        }
+        self.macros.insert(name, Rc::new(definition));
+    }
+
+    // 解析 ( 之后的参数列表，返回参数名、是否可变参数以及替换列表的起始位置
+    fn parameter_list(
+        &mut self,
+        line: &[PpToken],
+        mut index: usize,
+    ) -> Option<(Vec<String>, bool, usize)> {
+        let mut names: Vec<String> = Vec::new();
+        let mut variadic = false;
+        let invalid = |line: &[PpToken], index: usize| {
+            let location = line
+                .get(index)
+                .unwrap_or(&line[line.len() - 1])
+                .location
+                .clone();
+            PreprocessError::InvalidParameterList(location)
+        };
+        if line.get(index)?.is_delimiter(Delimiter::RightParenthesis) {
+            return Some((names, false, index + 1));
+        }
+        loop {
+            let Some(token) = line.get(index) else {
+                self.errors.push(invalid(line, index));
+                return None;
+            };
+            if token.is_delimiter(Delimiter::Ellipsis) {
+                variadic = true;
+            } else if let Some(name) = token.macro_name() {
+                if names.iter().any(|param| param == name) {
+                    self.errors.push(PreprocessError::DuplicateParameter {
+                        name: name.to_string(),
+                        location: token.location.clone(),
+                    });
+                    return None;
+                }
+                names.push(name.to_string());
+            } else {
+                self.errors.push(invalid(line, index));
+                return None;
+            }
+            index += 1;
+            match line.get(index) {
+                Some(token) if token.is_delimiter(Delimiter::RightParenthesis) => {
+                    return Some((names, variadic, index + 1));
+                }
+                Some(token) if token.is_delimiter(Delimiter::Comma) && !variadic => index += 1,
+                _ => {
+                    self.errors.push(invalid(line, index));
+                    return None;
+                }
+            }
+        }
+    }
+
+    // 检查替换列表中 # 与 ## 的位置，以及 __VA_ARGS__ 的使用
+    fn check_body(&mut self, definition: &Macro, body: &[PpToken]) -> bool {
+        for edge in [body.first(), body.last()].into_iter().flatten() {
+            if edge.is_delimiter(Delimiter::HashHash) {
+                self.errors
+                    .push(PreprocessError::PasteAtEdge(edge.location.clone()));
+                return false;
+            }
+        }
+        for (i, token) in body.iter().enumerate() {
+            if definition.params.is_some() && token.is_delimiter(Delimiter::Hash) {
+                let parameter = body
+                    .get(i + 1)
+                    .and_then(|next| definition.param_index(next));
+                if parameter.is_none() {
+                    self.errors.push(PreprocessError::StringifyNonParameter(
+                        token.location.clone(),
+                    ));
+                    return false;
+                }
+            }
+            if token.macro_name() == Some("__VA_ARGS__") && !definition.variadic {
+                self.errors.push(PreprocessError::VaArgsOutsideVariadic(
+                    token.location.clone(),
+                ));
+            }
+        }
+        true
+    }
+
+    // 取下一个未展开的 token：先取等待重新扫描的，from_file 时再从文件中读取
+    fn next_raw(&mut self, queue: &mut VecDeque<PpToken>, from_file: bool) -> Option<PpToken> {
+        match queue.pop_front() {
+            Some(token) => Some(token),
+            None if from_file => self.next_file_token(),
+            None => None,
+        }
+    }
+
+    // 取下一个完全展开后的 token
+    fn expand_next(&mut self, queue: &mut VecDeque<PpToken>, from_file: bool) -> Option<PpToken> {
+        loop {
+            let token = self.next_raw(queue, from_file)?;
+            let Some(name) = token.macro_name() else {
+                return Some(token);
+            };
+            if token.hide_set.contains(name) {
+                return Some(token);
+            }
+            let Some(definition) = self.macros.get(name).cloned() else {
+                return Some(token);
+            };
+            let name = name.to_string();
+            let replacement = if definition.params.is_none() {
+                let hide_set = with_name(&token.hide_set, &name);
+                self.substitute(&definition, &[], hide_set, &token)
+            } else {
+                // 函数式宏的名字后面必须跟着 (，否则不展开
+                let next = self.next_raw(queue, from_file);
+                match next {
+                    Some(next) if next.is_delimiter(Delimiter::LeftParenthesis) => {
+                        let Some((args, consumed)) = self.collect_arguments(
+                            &definition,
+                            &name,
+                            &token,
+                            next,
+                            queue,
+                            from_file,
+                        ) else {
+                            return Some(token);
+                        };
+                        let Some(args) = self.check_arguments(&definition, &name, &token, args)
+                        else {
+                            // 实参个数不对时宏名按普通标识符输出，( 到 ) 放回去重新扫描
+                            for token in consumed.into_iter().rev() {
+                                queue.push_front(token);
+                            }
+                            return Some(token);
+                        };
+                        let right = consumed.last().unwrap_or(&token);
+                        let shared: HashSet<String> = token
+                            .hide_set
+                            .intersection(&right.hide_set)
+                            .cloned()
+                            .collect();
+                        let hide_set = with_name(&Rc::new(shared), &name);
+                        self.substitute(&definition, &args, hide_set, &token)
+                    }
+                    Some(next) => {
+                        queue.push_front(next);
+                        return Some(token);
+                    }
+                    None => return Some(token),
+                }
+            };
+            for token in replacement.into_iter().rev() {
+                queue.push_front(token);
+            }
+        }
+    }
+
+    // 读取 ( 之后的实参直到匹配的 )，实参之间以最外层的逗号分隔，可变参数部分保留逗号；
+    // 同时返回从 ( 到 ) 读取的全部 token
+    fn collect_arguments(
+        &mut self,
+        definition: &Macro,
+        name: &str,
+        invocation: &PpToken,
+        left: PpToken,
+        queue: &mut VecDeque<PpToken>,
+        from_file: bool,
+    ) -> Option<(Vec<Vec<PpToken>>, Vec<PpToken>)> {
+        let named = definition.params.as_ref().map_or(0, |params| params.len());
+        let mut args: Vec<Vec<PpToken>> = vec![Vec::new()];
+        let mut consumed = vec![left];
+        let mut depth = 0;
+        while let Some(token) = self.next_raw(queue, from_file) {
+            if token.token == Token::EOF {
+                queue.push_front(token);
+                break;
+            }
+            consumed.push(token.clone());
+            if token.is_delimiter(Delimiter::LeftParenthesis) {
+                depth += 1;
+            } else if token.is_delimiter(Delimiter::RightParenthesis) {
+                if depth == 0 {
+                    return Some((args, consumed));
+                }
+                depth -= 1;
+            } else if token.is_delimiter(Delimiter::Comma)
+                && depth == 0
+                && !(definition.variadic && args.len() > named)
+            {
+                args.push(Vec::new());
+                continue;
+            }
+            if let Some(arg) = args.last_mut() {
+                arg.push(token);
+            }
+        }
+        self.errors.push(PreprocessError::UnterminatedArgumentList {
+            name: name.to_string(),
+            location: invocation.location.clone(),
+        });
+        // 参数列表没有闭合时宏名按普通标识符输出，其后的 token 放回去重新扫描
+        for token in consumed.into_iter().rev() {
+            queue.push_front(token);
+        }
+        None
+    }
+
+    // 核对实参个数，可变参数宏可以省略可变部分
+    fn check_arguments(
+        &mut self,
+        definition: &Macro,
+        name: &str,
+        invocation: &PpToken,
+        mut args: Vec<Vec<PpToken>>,
+    ) -> Option<Vec<Vec<PpToken>>> {
+        let expected = definition.params.as_ref().map_or(0, |params| params.len());
+        // F() 对于没有参数的宏是零个实参
+        if expected == 0 && args.len() == 1 && args[0].is_empty() {
+            args.clear();
+        }
+        if definition.variadic {
+            if args.len() == expected {
+                args.push(Vec::new());
+            }
+            if args.len() == expected + 1 {
+                return Some(args);
+            }
+        } else if args.len() == expected {
+            return Some(args);
+        }
+        self.errors.push(PreprocessError::WrongArgumentCount {
+            name: name.to_string(),
+            expected,
+            found: args.len(),
+            location: invocation.location.clone(),
+        });
+        None
+    }
+
+    // 按照参数替换替换列表，处理 # 与 ##，最后为结果加上隐藏集合
+    fn substitute(
+        &mut self,
+        definition: &Macro,
+        args: &[Vec<PpToken>],
+        hide_set: Rc<HashSet<String>>,
+        invocation: &PpToken,
+    ) -> Vec<PpToken> {
+        let body = match &definition.body {
+            MacroBody::Tokens(body) => body,
+            MacroBody::Builtin(builtin) => return self.builtin(*builtin, invocation),
+        };
+        let mut expanded: Vec<Option<Vec<PpToken>>> = vec![None; args.len()];
+        let mut items: Vec<Item> = Vec::new();
+        let mut i = 0;
+        while i < body.len() {
+            let token = &body[i];
+            // # 参数：字符串化未展开的实参
+            if token.is_delimiter(Delimiter::Hash) && definition.params.is_some() {
+                if let Some(index) = body
+                    .get(i + 1)
+                    .and_then(|next| definition.param_index(next))
+                {
+                    items.push(Item::Token(self.stringify(&args[index], token)));
+                    i += 2;
+                    continue;
+                }
+            }
+            if token.is_delimiter(Delimiter::HashHash) {
+                i += 1;
+                let mut right = Vec::new();
+                // 检查过的替换列表不会以 ## 结尾，这里仍不假定 ## 后面一定有 token
+                let Some(next) = body.get(i) else {
+                    continue;
+                };
+                let hash_parameter = body
+                    .get(i + 1)
+                    .filter(|_| next.is_delimiter(Delimiter::Hash) && definition.params.is_some())
+                    .and_then(|parameter| definition.param_index(parameter));
+                if let Some(index) = hash_parameter {
+                    right.push(self.stringify(&args[index], next));
+                    i += 2;
+                } else if let Some(index) = definition.param_index(next) {
+                    // GNU 扩展：, ## __VA_ARGS__ 在可变参数为空时删除逗号
+                    let left_is_comma = matches!(
+                        items.last(),
+                        Some(Item::Token(left)) if left.is_delimiter(Delimiter::Comma)
+                    );
+                    if definition.variadic && index == args.len() - 1 && left_is_comma {
+                        if args[index].is_empty() {
+                            items.pop();
+                        }
+                        items.extend(args[index].iter().cloned().map(Item::Token));
+                        i += 1;
+                        continue;
+                    }
+                    right.extend(args[index].iter().cloned());
+                    i += 1;
+                } else {
+                    right.push(next.clone());
+                    i += 1;
+                }
+                self.paste(&mut items, right);
+                continue;
+            }
+            if let Some(index) = definition.param_index(token) {
+                let pasted = body
+                    .get(i + 1)
+                    .is_some_and(|next| next.is_delimiter(Delimiter::HashHash));
+                let arg = if pasted {
+                    args[index].clone()
+                } else {
+                    // 实参先单独完全展开，再替换进来
+                    if expanded[index].is_none() {
+                        expanded[index] = Some(self.expand_list(args[index].clone()));
+                    }
+                    expanded[index].clone().unwrap_or_default()
+                };
+                if arg.is_empty() {
+                    items.push(Item::Placemarker);
+                }
+                for (j, mut arg_token) in arg.into_iter().enumerate() {
+                    if j == 0 {
+                        arg_token.leading_space = token.leading_space;
+                    }
+                    items.push(Item::Token(arg_token));
+                }
+                i += 1;
+                continue;
+            }
+            items.push(Item::Token(token.clone()));
+            i += 1;
+        }
+        let expanded_from = invocation
+            .expanded_from
+            .clone()
+            .or_else(|| Some(invocation.location.clone()));
+        let mut tokens: Vec<PpToken> = items
+            .into_iter()
+            .filter_map(|item| match item {
+                Item::Token(token) => Some(token),
+                Item::Placemarker => None,
+            })
+            .map(|mut token| {
+                token.hide_set = union(&token.hide_set, &hide_set);
+                token.expanded_from = expanded_from.clone();
+                token
+            })
+            .collect();
+        if let Some(first) = tokens.first_mut() {
+            first.leading_space = invocation.leading_space;
+        }
+        tokens
+    }
+
+    // 将实参单独展开，不会读取实参之后的 token
+    fn expand_list(&mut self, tokens: Vec<PpToken>) -> Vec<PpToken> {
+        let mut queue = VecDeque::from(tokens);
+        let mut result = Vec::new();
+        while let Some(token) = self.expand_next(&mut queue, false) {
+            result.push(token);
+        }
+        result
+    }
+
+    // 将 right 的第一个 token 拼接到 items 的最后一个之后
+    fn paste(&mut self, items: &mut Vec<Item>, right: Vec<PpToken>) {
+        let mut right = right.into_iter();
+        let Some(first) = right.next() else {
+            return; // 右侧为空，保留左侧
+        };
+        match items.pop() {
+            Some(Item::Token(left)) => {
+                let text = format!("{}{}", left.spelling, first.spelling);
+                match self.lex_text(&text, Some(&left)) {
+                    Ok(tokens) if tokens.len() == 1 => {
+                        let leading_space = left.leading_space;
+                        let hide_set = union(&left.hide_set, &first.hide_set);
+                        let token = tokens.into_iter().next().unwrap_or(left);
+                        items.push(Item::Token(PpToken {
+                            leading_space,
+                            hide_set,
+                            ..token
+                        }));
+                    }
+                    _ => {
+                        self.errors.push(PreprocessError::InvalidPaste {
+                            text,
+                            location: left.location.clone(),
+                        });
+                        items.push(Item::Token(left));
+                        items.push(Item::Token(first));
+                    }
+                }
+            }
+            Some(Item::Placemarker) | None => items.push(Item::Token(first)),
+        }
+        items.extend(right.map(Item::Token));
+    }
+
+    // 将实参转换为字符串字面量，token 之间的空白合并为一个空格
+    fn stringify(&mut self, arg: &[PpToken], hash: &PpToken) -> PpToken {
+        let mut text = String::from("\"");
+        for (i, token) in arg.iter().enumerate() {
+            if i > 0 && token.leading_space {
+                text.push(' ');
+            }
+            match token.token {
+                Token::Strings(_) | Token::CharConstant(_) => {
+                    for c in token.spelling.chars() {
+                        if c == '"' || c == '\\' {
+                            text.push('\\');
+                        }
+                        text.push(c);
+                    }
+                }
+                _ => text.push_str(&token.spelling),
+            }
+        }
+        text.push('"');
+        // 与字符串字面量一样解码转义序列，得到字符串的值
+        let value = match Lexer::new(&text).next_token() {
+            Some(Ok((Token::Strings(literal), _))) => literal.value,
+            _ => text[1..text.len() - 1].bytes().map(u32::from).collect(),
+        };
+        PpToken {
+            token: Token::Strings(StringLiteral {
+                encoding: Encoding::Plain,
+                raw: Cow::Owned(text.clone()),
+                value,
+            }),
+            location: hash.location.clone(),
+            expanded_from: hash.expanded_from.clone(),
+            spelling: text,
+            leading_space: hash.leading_space,
+            hide_set: Rc::default(),
+        }
+    }
+
+    fn builtin(&mut self, builtin: Builtin, invocation: &PpToken) -> Vec<PpToken> {
+        // 宏展开得到的 __LINE__ 取最外层宏调用所在的行
+        let location = invocation
+            .expanded_from
+            .as_ref()
+            .unwrap_or(&invocation.location);
+        let (token, spelling) = match builtin {
+            Builtin::Line => {
+                let offset = self
+                    .files
+                    .get(location.span().file_id)
+                    .map_or(0, |file| file.line_offset);
+                let line = (location.row() as i64 + offset) as u64;
+                (integer_token(invocation, line).token, line.to_string())
+            }
+            Builtin::File => {
+                let name = self
+                    .files
+                    .get(location.span().file_id)
+                    .map_or("<built-in>", |file| {
+                        file.presumed_name.as_deref().unwrap_or(&file.name)
+                    })
+                    .to_string();
+                let mut text = String::from("\"");
+                for c in name.chars() {
+                    if c == '"' || c == '\\' {
+                        text.push('\\');
+                    }
+                    text.push(c);
+                }
+                text.push('"');
+                let literal = StringLiteral {
+                    encoding: Encoding::Plain,
+                    raw: Cow::Owned(text.clone()),
+                    value: name.bytes().map(u32::from).collect(),
+                };
+                (Token::Strings(literal), text)
+            }
+        };
+        vec![PpToken {
+            token,
+            location: invocation.location.clone(),
+            expanded_from: invocation.expanded_from.clone(),
+            spelling,
+            leading_space: invocation.leading_space,
+            hide_set: Rc::default(),
+        }]
+    }
+}
+
+fn with_name(hide_set: &Rc<HashSet<String>>, name: &str) -> Rc<HashSet<String>> {
+    if hide_set.contains(name) {
+        return hide_set.clone();
+    }
+    let mut names = (**hide_set).clone();
+    names.insert(name.to_string());
+    Rc::new(names)
+}
+
+fn union(left: &Rc<HashSet<String>>, right: &Rc<HashSet<String>>) -> Rc<HashSet<String>> {
+    if right.is_subset(left) {
+        return left.clone();
+    }
+    if left.is_subset(right) {
+        return right.clone();
+    }
+    Rc::new(left.union(right).cloned().collect())
+}
+
+// 值为 value 的十进制整数常量，位置取自 origin
+fn integer_token(origin: &PpToken, value: u64) -> PpToken {
+    let constant = IntegerConstant {
+        value,
+        radix: Radix::Decimal,
+        suffix: IntegerSuffix::None,
+        ty: IntegerType::Int,
+    };
+    PpToken {
+        token: Token::Numbers(Number::Integer(constant)),
+        location: origin.location.clone(),
+        expanded_from: origin.expanded_from.clone(),
+        spelling: value.to_string(),
+        leading_space: origin.leading_space,
+        hide_set: Rc::default(),
+    }
+}
+
+// #if 表达式中的值，按 intmax_t 或 uintmax_t 计算
+#[derive(Debug, Clone, Copy)]
+struct Value {
+    bits: u64,
+    unsigned: bool,
+    known: bool, // 部分预处理时依赖未知的宏则为 false
+}
+
+impl Value {
+    fn signed(value: i64) -> Value {
+        Value {
+            bits: value as u64,
+            unsigned: false,
+            known: true,
+        }
+    }
+
+    fn boolean(value: bool) -> Value {
+        Value::signed(i64::from(value))
+    }
+
+    fn unknown() -> Value {
+        Value {
+            known: false,
+            ..Value::signed(0)
+        }
+    }
+
+    fn is_true(&self) -> bool {
+        self.known && self.bits != 0
+    }
+
+    fn is_false(&self) -> bool {
+        self.known && self.bits == 0
+    }
+
+    fn is_negative(&self) -> bool {
+        !self.unsigned && (self.bits as i64) < 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOperator {
+    LogicalOr,
+    LogicalAnd,
+    BitwiseOr,
+    BitwiseXor,
+    BitwiseAnd,
+    Equal,
+    NotEqual,
+    LessThan,
+    GreaterThan,
+    LessThanOrEqual,
+    GreaterThanOrEqual,
+    LeftShift,
+    RightShift,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulus,
+}
+
+impl BinaryOperator {
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::LogicalOr => 1,
+            BinaryOperator::LogicalAnd => 2,
+            BinaryOperator::BitwiseOr => 3,
+            BinaryOperator::BitwiseXor => 4,
+            BinaryOperator::BitwiseAnd => 5,
+            BinaryOperator::Equal | BinaryOperator::NotEqual => 6,
+            BinaryOperator::LessThan
+            | BinaryOperator::GreaterThan
+            | BinaryOperator::LessThanOrEqual
+            | BinaryOperator::GreaterThanOrEqual => 7,
+            BinaryOperator::LeftShift | BinaryOperator::RightShift => 8,
+            BinaryOperator::Add | BinaryOperator::Subtract => 9,
+            BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulus => 10,
+        }
+    }
+}
+
+// #if 的整数常量表达式，evaluate 为 false 时只检查语法，不报告除以零
+struct ConditionParser<'a> {
+    tokens: &'a [PpToken],
+    position: usize,
+    directive: &'a PpToken,
+    known: Option<&'a HashSet<String>>, // 部分预处理时已知的宏，其余标识符的值未知
+    boolean_literals: bool,             // C23 与 C++ 中 true、false 是关键字
+}
+
+impl<'a> ConditionParser<'a> {
+    fn parse(&mut self) -> Result<Value, PreprocessError> {
+        let value = self.expression(true)?;
+        match self.tokens.get(self.position) {
+            Some(token) => Err(self.invalid(
+                format!(
+                    "missing binary operator before token \"{}\"",
+                    token.spelling
+                ),
+                token,
+            )),
+            None => Ok(value),
+        }
+    }
+
+    fn invalid(&self, message: String, token: &PpToken) -> PreprocessError {
+        PreprocessError::InvalidCondition {
+            message,
+            location: token.location.clone(),
+        }
+    }
+
+    fn peek(&self) -> Option<&'a PpToken> {
+        self.tokens.get(self.position)
+    }
+
+    fn eat_delimiter(&mut self, delimiter: Delimiter) -> bool {
+        let matched = self
+            .peek()
+            .is_some_and(|token| token.is_delimiter(delimiter));
+        if matched {
+            self.position += 1;
+        }
+        matched
+    }
+
+    // 逗号表达式，取最后一项的值
+    fn expression(&mut self, evaluate: bool) -> Result<Value, PreprocessError> {
+        let mut value = self.conditional(evaluate)?;
+        while self.eat_delimiter(Delimiter::Comma) {
+            value = self.conditional(evaluate)?;
+        }
+        Ok(value)
+    }
+
+    fn conditional(&mut self, evaluate: bool) -> Result<Value, PreprocessError> {
+        let condition = self.binary(1, evaluate)?;
+        if !self.eat_delimiter(Delimiter::ConditionalOperator) {
+            return Ok(condition);
+        }
+        let then = self.expression(evaluate && condition.is_true())?;
+        if !self.eat_delimiter(Delimiter::ConditionalSeparator) {
+            let token = self.peek().unwrap_or(self.directive);
+            return Err(self.invalid("expected ':' in #if expression".to_string(), token));
+        }
+        let otherwise = self.conditional(evaluate && condition.is_false())?;
+        if !condition.known {
+            return Ok(Value::unknown());
+        }
+        let mut value = if condition.is_true() { then } else { otherwise };
+        value.unsigned = then.unsigned || otherwise.unsigned;
+        Ok(value)
+    }
+
+    // 当前 token 作为二元运算符
+    fn binary_operator(&self) -> Option<BinaryOperator> {
+        let Token::Operators(operator) = &self.peek()?.token else {
+            return None;
+        };
+        let operator = match operator {
+            Operator::LogicalOr => BinaryOperator::LogicalOr,
+            Operator::LogicalAnd => BinaryOperator::LogicalAnd,
+            Operator::BitwiseOr => BinaryOperator::BitwiseOr,
+            Operator::BitwiseXor => BinaryOperator::BitwiseXor,
+            Operator::Amp => BinaryOperator::BitwiseAnd,
+            Operator::Equal => BinaryOperator::Equal,
+            Operator::NotEqual => BinaryOperator::NotEqual,
+            Operator::LessThan => BinaryOperator::LessThan,
+            Operator::GreaterThan => BinaryOperator::GreaterThan,
+            Operator::LessThanOrEqual => BinaryOperator::LessThanOrEqual,
+            Operator::GreaterThanOrEqual => BinaryOperator::GreaterThanOrEqual,
+            Operator::LeftShift => BinaryOperator::LeftShift,
+            Operator::RightShift => BinaryOperator::RightShift,
+            Operator::Plus => BinaryOperator::Add,
+            Operator::Minus => BinaryOperator::Subtract,
+            Operator::Star => BinaryOperator::Multiply,
+            Operator::Divide => BinaryOperator::Divide,
+            Operator::Modulus => BinaryOperator::Modulus,
+            _ => return None,
+        };
+        Some(operator)
+    }
+
+    // 按优先级爬升处理二元运算符
+    fn binary(&mut self, min_precedence: u8, evaluate: bool) -> Result<Value, PreprocessError> {
+        let mut left = self.unary(evaluate)?;
+        while let Some(operator) = self.binary_operator() {
+            let precedence = operator.precedence();
+            if precedence < min_precedence {
+                break;
+            }
+            let token = &self.tokens[self.position];
+            self.position += 1;
+            left = match operator {
+                // && 与 || 短路，不计算的一侧不报告除以零，一侧已能决定结果时另一侧可以未知
+                BinaryOperator::LogicalAnd => {
+                    let right = self.binary(precedence + 1, evaluate && left.is_true())?;
+                    if left.is_false() || right.is_false() {
+                        Value::boolean(false)
+                    } else if left.known && right.known {
+                        Value::boolean(true)
+                    } else {
+                        Value::unknown()
+                    }
+                }
+                BinaryOperator::LogicalOr => {
+                    let right = self.binary(precedence + 1, evaluate && left.is_false())?;
+                    if left.is_true() || right.is_true() {
+                        Value::boolean(true)
+                    } else if left.known && right.known {
+                        Value::boolean(false)
+                    } else {
+                        Value::unknown()
+                    }
+                }
+                _ => {
+                    let right = self.binary(precedence + 1, evaluate)?;
+                    self.apply(operator, left, right, evaluate, token)?
+                }
+            };
+        }
+        Ok(left)
+    }
+
+    // 按照 C 的寻常算术转换，任一操作数为无符号时按无符号计算
+    fn apply(
+        &self,
+        operator: BinaryOperator,
+        left: Value,
+        right: Value,
+        evaluate: bool,
+        token: &PpToken,
+    ) -> Result<Value, PreprocessError> {
+        if !left.known || !right.known {
+            return Ok(Value::unknown());
+        }
+        let unsigned = left.unsigned || right.unsigned;
+        let (a, b) = (left.bits, right.bits);
+        let arithmetic = |bits: u64| Value {
+            bits,
+            unsigned,
+            known: true,
+        };
+        let value = match operator {
+            BinaryOperator::BitwiseOr => arithmetic(a | b),
+            BinaryOperator::BitwiseXor => arithmetic(a ^ b),
+            BinaryOperator::BitwiseAnd => arithmetic(a & b),
+            BinaryOperator::Equal => Value::boolean(a == b),
+            BinaryOperator::NotEqual => Value::boolean(a != b),
+            BinaryOperator::LessThan
+            | BinaryOperator::GreaterThan
+            | BinaryOperator::LessThanOrEqual
+            | BinaryOperator::GreaterThanOrEqual => {
+                let ordering = if unsigned {
+                    a.cmp(&b)
+                } else {
+                    (a as i64).cmp(&(b as i64))
+                };
+                Value::boolean(match operator {
+                    BinaryOperator::LessThan => ordering.is_lt(),
+                    BinaryOperator::GreaterThan => ordering.is_gt(),
+                    BinaryOperator::LessThanOrEqual => ordering.is_le(),
+                    _ => ordering.is_ge(),
+                })
+            }
+            // 移位的结果类型取左操作数的类型，负数移位量表示反方向移位
+            BinaryOperator::LeftShift | BinaryOperator::RightShift => {
+                let left_shift = (operator == BinaryOperator::LeftShift) != right.is_negative();
+                let amount = if right.is_negative() {
+                    (b as i64).unsigned_abs()
+                } else {
+                    b
+                };
+                let bits = match (left_shift, amount >= 64) {
+                    (true, true) => 0,
+                    (true, false) => a << amount,
+                    (false, true) if left.is_negative() => u64::MAX,
+                    (false, true) => 0,
+                    (false, false) if left.unsigned => a >> amount,
+                    (false, false) => ((a as i64) >> amount) as u64,
+                };
+                Value {
+                    bits,
+                    unsigned: left.unsigned,
+                    known: true,
+                }
+            }
+            BinaryOperator::Add => arithmetic(a.wrapping_add(b)),
+            BinaryOperator::Subtract => arithmetic(a.wrapping_sub(b)),
+            BinaryOperator::Multiply => arithmetic(a.wrapping_mul(b)),
+            BinaryOperator::Divide | BinaryOperator::Modulus => {
+                if b == 0 {
+                    if evaluate {
+                        return Err(PreprocessError::DivisionByZero(token.location.clone()));
+                    }
+                    return Ok(arithmetic(0));
+                }
+                let divide = operator == BinaryOperator::Divide;
+                let bits = match (unsigned, divide) {
+                    (true, true) => a / b,
+                    (true, false) => a % b,
+                    (false, true) => (a as i64).wrapping_div(b as i64) as u64,
+                    (false, false) => (a as i64).wrapping_rem(b as i64) as u64,
+                };
+                arithmetic(bits)
+            }
+            BinaryOperator::LogicalOr | BinaryOperator::LogicalAnd => {
+                Value::boolean(left.is_true() || right.is_true())
+            }
+        };
+        Ok(value)
+    }
+
+    fn unary(&mut self, evaluate: bool) -> Result<Value, PreprocessError> {
+        let Some(token) = self.peek() else {
+            let message = match self.position {
+                0 => format!("#{} with no expression", self.directive.spelling),
+                _ => "expected value in expression".to_string(),
+            };
+            let token = self.tokens.last().unwrap_or(self.directive);
+            return Err(self.invalid(message, token));
+        };
+        let operator = match &token.token {
+            Token::Operators(operator) => operator.clone(),
+            _ => return self.primary(evaluate),
+        };
+        self.position += 1;
+        let operand = match operator {
+            Operator::Plus | Operator::Minus | Operator::BitwiseNot | Operator::LogicalNot => {
+                self.unary(evaluate)?
+            }
+            _ => {
+                let message = format!(
+                    "token \"{}\" is not valid in preprocessor expressions",
+                    token.spelling
+                );
+                return Err(self.invalid(message, token));
+            }
+        };
+        Ok(match operator {
+            Operator::Minus => Value {
+                bits: operand.bits.wrapping_neg(),
+                ..operand
+            },
+            Operator::BitwiseNot => Value {
+                bits: !operand.bits,
+                ..operand
+            },
+            Operator::LogicalNot if !operand.known => operand,
+            Operator::LogicalNot => Value::boolean(operand.is_false()),
+            _ => operand,
+        })
+    }
+
+    fn primary(&mut self, evaluate: bool) -> Result<Value, PreprocessError> {
+        let token = &self.tokens[self.position];
+        self.position += 1;
+        match &token.token {
+            Token::Delimiters(Delimiter::LeftParenthesis) => {
+                let value = self.expression(evaluate)?;
+                if !self.eat_delimiter(Delimiter::RightParenthesis) {
+                    let token = self.peek().unwrap_or(token);
+                    return Err(self.invalid("missing ')' in expression".to_string(), token));
+                }
+                Ok(value)
+            }
+            Token::Numbers(Number::Integer(constant)) => {
+                // 带 u 后缀或超出 intmax_t 范围的常量是无符号的
+                let unsigned = matches!(
+                    constant.suffix,
+                    IntegerSuffix::Unsigned
+                        | IntegerSuffix::UnsignedLong
+                        | IntegerSuffix::UnsignedLongLong
+                ) || constant.value > i64::MAX as u64;
+                Ok(Value {
+                    bits: constant.value,
+                    unsigned,
+                    known: true,
+                })
+            }
+            Token::Numbers(Number::Float(_)) => Err(self.invalid(
+                "floating constant in preprocessor expression".to_string(),
+                token,
+            )),
+            Token::CharConstant(literal) => Ok(Value::signed(literal.value)),
+            // C23 与 C++ 中 true 与 false 在 #if 中的值为 1 和 0，其余标识符都视为 0
+            Token::Identifiers(_) | Token::Keywords(_) => {
+                let name = token.spelling.as_str();
+                if self.boolean_literals && matches!(name, "true" | "false") {
+                    return Ok(Value::boolean(name == "true"));
+                }
+                if self.known.is_some_and(|known| !known.contains(name)) {
+                    return Ok(Value::unknown());
+                }
+                Ok(Value::boolean(false))
+            }
+            _ => {
+                let message = format!(
+                    "token \"{}\" is not valid in preprocessor expressions",
+                    token.spelling
+                );
+                Err(self.invalid(message, token))
+            }
+        }
+    }
+}
+
+// gap 为上一个 token 与指令的 # 之间的空白与注释，返回被删除的指令行在 gap 中的起点：
+// 注释与之前的换行都保留，只删除 # 所在逻辑行中注释之后的空白与续行
+fn directive_start(gap: &str) -> usize {
+    let bytes = gap.as_bytes();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"/*") {
+            i = gap[i + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |end| i + 2 + end + 2);
+            start = i;
+        } else if bytes[i..].starts_with(b"//") {
+            // 行注释到不是续行的换行为止
+            i += 2;
+            while i < bytes.len() && (bytes[i] != b'\n' || is_splice(&bytes[..i])) {
+                i += 1;
+            }
+        } else if bytes[i] == b'\n' && !is_splice(&bytes[..i]) {
+            i += 1;
+            start = i;
+        } else {
+            i += 1;
+        }
+    }
+    start
+}
+
+// 换行之前是否是续行的 \
+fn is_splice(before: &[u8]) -> bool {
+    before
+        .strip_suffix(b"\r")
+        .unwrap_or(before)
+        .ends_with(b"\\")
+}
+
+// 读取源文件，不是合法 UTF-8 的字节替换为 U+FFFD
+fn read_source(path: &Path) -> io::Result<String> {
+    let bytes = fs::read(path)?;
+    Ok(match String::from_utf8(bytes) {
+        Ok(source) => source,
+        Err(error) => String::from_utf8_lossy(error.as_bytes()).into_owned(),
+    })
+}
+
+// 检测 #ifndef X ... #endif 形式的保护宏：文件中除了这一组条件之外没有其他 token
+fn include_guard(tokens: &VecDeque<Result<PpToken, PreprocessError>>) -> Option<String> {
+    let tokens: Vec<&PpToken> = tokens
+        .iter()
+        .filter_map(|item| item.as_ref().ok())
+        .collect();
+    let spellings: Vec<&str> = tokens
+        .iter()
+        .map(|token| match token.token {
+            Token::Directive => "#",
+            Token::EndOfDirective => "\n",
+            _ => token.spelling.as_str(),
+        })
+        .collect();
+    let (guard, mut index) = match spellings.as_slice() {
+        ["#", "ifndef", name, "\n", ..] => (*name, 4),
+        ["#", "if", "!", "defined", name, "\n", ..] => (*name, 6),
+        ["#", "if", "!", "defined", "(", name, ")", "\n", ..] => (*name, 8),
+        _ => return None,
+    };
+    let mut depth = 1;
+    while index < spellings.len() {
+        if spellings[index] == "#" {
+            match spellings.get(index + 1).copied() {
+                Some("if" | "ifdef" | "ifndef") => depth += 1,
+                Some("elif" | "else" | "elifdef" | "elifndef") if depth == 1 => return None,
+                Some("endif") => {
+                    depth -= 1;
+                    if depth == 0 {
+                        // #endif 这一行之后只能是文件结尾
+                        let end = index + spellings[index..].iter().position(|&s| s == "\n")?;
+                        return tokens
+                            .iter()
+                            .skip(end + 1)
+                            .all(|token| token.token == Token::EOF)
+                            .then(|| guard.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        index += 1;
+    }
+    None
+}
+
+// __DATE__ 与 __TIME__ 的值，按 UTC 计算
+fn build_date_time() -> (String, String) {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let days = (seconds / 86400) as i64;
+    let time = seconds % 86400;
+    // 由距 1970-01-01 的天数推算公历日期
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let date = format!("\"{} {:>2} {}\"", MONTHS[(month - 1) as usize], day, year);
+    let time = format!(
+        "\"{:02}:{:02}:{:02}\"",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    );
+    (date, time)
+}
@@ -0,0 +1,30 @@
+// 整段输入时 token 的拼写直接借用输入，不产生拷贝
+use lexer::lexer::{Lexer, Token};
+use std::borrow::Cow;
+
+// 借用的文本必须位于输入之中
+fn borrows_from(text: Cow<'_, str>, source: &str) -> bool {
+    let range = source.as_bytes().as_ptr_range();
+    matches!(text, Cow::Borrowed(text) if range.contains(&text.as_ptr()))
+}
+
+#[test]
+fn spellings_borrow_from_the_input() {
+    let source = "#include <stdio.h>\nint naïve = L'x' + sizeof \"str\\n\";\n";
+    let mut spellings = Vec::new();
+    for item in Lexer::new(source) {
+        let text = match item.unwrap().0 {
+            Token::Identifiers(name) => name,
+            Token::HeaderName(name) => name,
+            Token::Strings(literal) => literal.raw,
+            Token::CharConstant(literal) => literal.raw,
+            _ => continue,
+        };
+        spellings.push(text.to_string());
+        assert!(borrows_from(text, source), "{:?}", spellings.last());
+    }
+    assert_eq!(
+        spellings,
+        ["include", "<stdio.h>", "naïve", "L'x'", "\"str\\n\""]
+    );
+}
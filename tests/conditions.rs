@@ -0,0 +1,106 @@
+// #if 表达式的求值：优先级、defined、无符号运算、除以零与 true/false
+use lexer::lexer::{Language, LanguageStandard, Token};
+use lexer::preprocessor::{PreprocessError, Preprocessor};
+
+// 预处理后剩下的 token 文本，以空格分隔
+fn output(preprocessor: &mut Preprocessor, source: &str) -> (String, Vec<PreprocessError>) {
+    let (tokens, errors) = preprocessor.preprocess("main.c", source);
+    let text: Vec<String> = tokens
+        .into_iter()
+        .filter(|token| token.token != Token::EOF)
+        .map(|token| token.spelling)
+        .collect();
+    (text.join(" "), errors)
+}
+
+#[test]
+fn true_and_false_are_keywords_only_in_c23_and_cpp() {
+    let source = "#if true\nt\n#endif\n#if !false\nf\n#endif\n";
+    let (text, errors) = output(&mut Preprocessor::new(), source);
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(text, "f");
+    let mut c23 = Preprocessor::new().with_standard(LanguageStandard::C23);
+    assert_eq!(output(&mut c23, source).0, "t f");
+    let mut cpp = Preprocessor::new().with_language(Language::Cpp);
+    assert_eq!(output(&mut cpp, source).0, "t f");
+
+    // 部分预处理时，C17 中的 true 是未知的标识符，条件保持原样
+    let mut preprocessor = Preprocessor::new();
+    let (text, _) = preprocessor.preprocess_partial("main.c", "#if true\nt\n#endif\n");
+    assert_eq!(text, "#if true\nt\n#endif\n");
+    let mut preprocessor = Preprocessor::new().with_standard(LanguageStandard::C23);
+    let (text, _) = preprocessor.preprocess_partial("main.c", "#if true\nt\n#endif\n");
+    assert_eq!(text, "t\n");
+}
+
+// 每个条件为真时输出对应的标识符
+fn selected(conditions: &[&str]) -> (String, Vec<PreprocessError>) {
+    let mut source = String::new();
+    for (i, condition) in conditions.iter().enumerate() {
+        source.push_str(&format!("#if {}\nc{}\n#endif\n", condition, i));
+    }
+    output(&mut Preprocessor::new(), &source)
+}
+
+#[test]
+fn precedence_and_associativity() {
+    let (text, errors) = selected(&[
+        "1 + 2 * 3 == 7",
+        "(1 + 2) * 3 == 9",
+        "10 - 4 - 3 == 3",
+        "1 << 2 + 1 == 8",
+        "1 | 2 ^ 3 & 4 == 3",
+        "!0 && 0 || 1",
+        "0 ? 1 : 0 ? 2 : 3",
+        "-1 < 0 && ~0 == -1",
+        "2 > 1 == 1 != 0",
+        "(0, 1)",
+    ]);
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(text, "c0 c1 c2 c3 c4 c5 c6 c7 c8 c9");
+}
+
+#[test]
+fn defined_and_unknown_identifiers() {
+    let source = "#define A 0\n#define F(x) x\n\
+#if defined A && defined(F) && !defined B\nyes\n#endif\n\
+#if A || B || C\nno\n#endif\n\
+#if F(1) + A\nf\n#endif\n";
+    let (text, errors) = output(&mut Preprocessor::new(), source);
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(text, "yes f");
+}
+
+#[test]
+fn unsigned_arithmetic() {
+    let (text, errors) = selected(&[
+        "-1 > 0u",
+        "-1 < 0",
+        "0u - 1 == 18446744073709551615u",
+        "18446744073709551615u / 2 == 9223372036854775807",
+        "(0 ? 1u : -1) > 0",
+        "-1 / 2u > 1",
+        // 有符号运算溢出时按补码回绕
+        "0x7fffffffffffffff + 1 < 0",
+    ]);
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(text, "c0 c1 c2 c3 c4 c5 c6");
+}
+
+#[test]
+fn division_by_zero_is_an_error() {
+    let (text, errors) = selected(&["1 / 0", "1 % 0", "0 && 1 / 0", "1 || 1 % 0"]);
+    // 短路求值中没有计算的部分不报错
+    assert_eq!(text, "c3");
+    let reported: Vec<(String, usize)> = errors
+        .iter()
+        .map(|error| (error.to_string(), error.location().row()))
+        .collect();
+    assert_eq!(
+        reported,
+        [
+            ("division by zero in #if [E0120]".to_string(), 1),
+            ("division by zero in #if [E0120]".to_string(), 4),
+        ]
+    );
+}
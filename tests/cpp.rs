@@ -0,0 +1,79 @@
+// C++ 模式：原始字符串与用户定义字面量
+use lexer::lexer::{Language, LexError, Lexer, Token};
+
+fn lex_cpp(source: &str) -> (Vec<Token<'_>>, Vec<LexError>) {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    for item in Lexer::new(source).with_language(Language::Cpp) {
+        match item {
+            Ok((token, _)) => tokens.push(token),
+            Err(error) => errors.push(error),
+        }
+    }
+    assert_eq!(tokens.pop(), Some(Token::EOF));
+    (tokens, errors)
+}
+
+#[test]
+fn raw_strings() {
+    // 源码与原始字符串的内容，其中的转义、引号、换行与续行都保持原样
+    let table = [
+        ("R\"(a)\"", "a"),
+        ("R\"d(a)\"b)d\"", "a)\"b"),
+        ("u8R\"(x\\n)\"", "x\\n"),
+        ("LR\"x(line1\nline2)x\"", "line1\nline2"),
+        ("R\"(a\\\nb)\"", "a\\\nb"),
+    ];
+    for (source, value) in table {
+        let (tokens, errors) = lex_cpp(source);
+        assert!(errors.is_empty(), "{:?}: {:?}", source, errors);
+        match &tokens[..] {
+            [Token::Strings(literal)] => {
+                assert_eq!(literal.raw, source);
+                assert_eq!(literal.to_string_lossy(), value, "{:?}", source);
+            }
+            other => panic!("{:?}: {:?}", source, other),
+        }
+    }
+
+    // 分隔符超过16个字符时跳过本行剩余部分；未闭合的原始字符串一直延伸到输入末尾
+    let (tokens, errors) = lex_cpp("R\"abcdefghijklmnopq(x)abcdefghijklmnopq\"\nR\"( open");
+    assert_eq!(tokens, [Token::Error, Token::Error]);
+    let codes: Vec<&str> = errors.iter().map(LexError::code).collect();
+    assert_eq!(codes, ["E0022", "E0009"]);
+
+    // C 中 R 只是标识符
+    let tokens: Vec<Token> = Lexer::new("R\"(a)\"").map(|item| item.unwrap().0).collect();
+    assert_eq!(tokens[0], Token::Identifiers("R".into()));
+}
+
+#[test]
+fn user_defined_literals() {
+    // 源码、去掉后缀的常量的原始文本与后缀
+    let table = [
+        ("123_km", "123", "_km"),
+        ("1.5_m", "1.5", "_m"),
+        ("0x10_h", "0x10", "_h"),
+        ("12u_x", "12", "u_x"),
+        ("\"abc\"s", "\"abc\"", "s"),
+        ("'c'_x", "'c'", "_x"),
+        ("R\"(r)\"_raw", "R\"(r)\"", "_raw"),
+    ];
+    for (source, literal, suffix) in table {
+        let (tokens, errors) = lex_cpp(source);
+        assert!(errors.is_empty(), "{:?}: {:?}", source, errors);
+        let [Token::UserDefinedLiteral(udl)] = &tokens[..] else {
+            panic!("{:?}: {:?}", source, tokens);
+        };
+        assert_eq!(udl.suffix, suffix, "{:?}", source);
+        let (expected, _) = lex_cpp(literal);
+        assert_eq!(*udl.literal, expected[0], "{:?}", source);
+    }
+
+    // C 中这些后缀不合法
+    let errors: Vec<&str> = Lexer::new("123_km 1.5_m")
+        .filter_map(Result::err)
+        .map(|error| error.code())
+        .collect();
+    assert_eq!(errors, ["E0016", "E0019"]);
+}
@@ -0,0 +1,70 @@
+// 词法错误的错误码、严重程度与 Span
+use lexer::lexer::{LexError, Lexer, Severity, Span};
+use std::collections::HashSet;
+
+#[test]
+fn every_error_has_a_distinct_code_and_severity() {
+    let span = Span::new(2, 5, 7);
+    let errors = [
+        LexError::UnknownCharacter { ch: '@', span },
+        LexError::MalformedFloat(span),
+        LexError::MalformedExponent(span),
+        LexError::InvalidOctalDigit(span),
+        LexError::MissingHexDigits(span),
+        LexError::MissingBinaryDigits(span),
+        LexError::IntegerOverflow(span),
+        LexError::UnterminatedChar(span),
+        LexError::UnterminatedString(span),
+        LexError::EmptyCharConstant(span),
+        LexError::MultiCharConstant(span),
+        LexError::UnknownEscape { ch: 'q', span },
+        LexError::MissingHexEscapeDigits(span),
+        LexError::EscapeOutOfRange(span),
+        LexError::InvalidUniversalCharacter(span),
+        LexError::InvalidIntegerSuffix(span),
+        LexError::IntegerTooLargeForSigned(span),
+        LexError::MissingBinaryExponent(span),
+        LexError::InvalidFloatSuffix(span),
+        LexError::InvalidDigitSeparator(span),
+        LexError::InvalidIdentifierCharacter { ch: '€', span },
+        LexError::InvalidRawStringDelimiter(span),
+        LexError::UnterminatedComment(span),
+    ];
+    let codes: Vec<&str> = errors.iter().map(LexError::code).collect();
+    // 错误码按定义顺序编号，E 开头的是错误，W 开头的是警告
+    let numbers: Vec<&str> = codes.iter().map(|code| &code[1..]).collect();
+    let expected: Vec<String> = (1..=errors.len()).map(|n| format!("{:04}", n)).collect();
+    assert_eq!(numbers, expected);
+    assert_eq!(codes.iter().collect::<HashSet<_>>().len(), errors.len());
+    let warnings: Vec<&str> = errors
+        .iter()
+        .filter(|error| error.severity() == Severity::Warning)
+        .map(LexError::code)
+        .collect();
+    assert_eq!(warnings, ["W0011", "W0012", "W0017"]);
+    for error in &errors {
+        assert_eq!(
+            error.code().starts_with('E'),
+            error.severity() == Severity::Error
+        );
+        assert_eq!(error.span(), span);
+        assert!(error.to_string().ends_with(&format!(" [{}]", error.code())));
+    }
+}
+
+#[test]
+fn errors_are_reported_in_source_order() {
+    let errors: Vec<(&str, Span)> = Lexer::new("a @ 'ab' 0x \"s")
+        .filter_map(Result::err)
+        .map(|error| (error.code(), error.span()))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            ("E0001", Span::new(0, 2, 3)),
+            ("W0011", Span::new(0, 4, 8)),
+            ("E0005", Span::new(0, 9, 11)),
+            ("E0009", Span::new(0, 12, 14)),
+        ]
+    );
+}
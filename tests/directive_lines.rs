@@ -0,0 +1,76 @@
+// 预处理 token：行首的 # 开始指令行，头文件名只出现在 #include 之后
+use lexer::lexer::{Delimiter, Lexer, Span, Token};
+
+fn lex(source: &str) -> Vec<(Token<'_>, &str)> {
+    Lexer::new(source)
+        .map(|item| {
+            let (token, span) = item.unwrap();
+            (token, span.source_text(source))
+        })
+        .collect()
+}
+
+fn identifier(name: &str) -> Token<'_> {
+    Token::Identifiers(name.into())
+}
+
+#[test]
+fn header_names_only_after_include() {
+    let source = "#include <stdio.h>\n#include_next \"a b.h\"\nx < y.h > z\n#define H <c.h>\n";
+    let tokens = lex(source);
+    let headers: Vec<&str> = tokens
+        .iter()
+        .filter_map(|(token, _)| match token {
+            Token::HeaderName(name) => Some(&**name),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(headers, ["<stdio.h>", "\"a b.h\""]);
+    assert_eq!(
+        tokens[..4],
+        [
+            (Token::Directive, "#"),
+            (identifier("include"), "include"),
+            (Token::HeaderName("<stdio.h>".into()), "<stdio.h>"),
+            (Token::EndOfDirective, "\n"),
+        ]
+    );
+}
+
+#[test]
+fn hash_starts_a_directive_only_at_line_start() {
+    let source = "  # define S(x) #x ## y\na # b\n";
+    let tokens: Vec<Token> = lex(source).into_iter().map(|(token, _)| token).collect();
+    assert_eq!(
+        tokens,
+        [
+            Token::Directive,
+            identifier("define"),
+            identifier("S"),
+            Token::Delimiters(Delimiter::LeftParenthesis),
+            identifier("x"),
+            Token::Delimiters(Delimiter::RightParenthesis),
+            Token::Delimiters(Delimiter::Hash),
+            identifier("x"),
+            Token::Delimiters(Delimiter::HashHash),
+            identifier("y"),
+            Token::EndOfDirective,
+            identifier("a"),
+            Token::Delimiters(Delimiter::Hash),
+            identifier("b"),
+            Token::EOF,
+        ]
+    );
+
+    // 没有换行的最后一行指令仍以 EndOfDirective 结束
+    let spans: Vec<(Token, Span)> = Lexer::new("#endif").map(Result::unwrap).collect();
+    assert_eq!(
+        spans,
+        [
+            (Token::Directive, Span::new(0, 0, 1)),
+            (identifier("endif"), Span::new(0, 1, 6)),
+            (Token::EndOfDirective, Span::new(0, 6, 6)),
+            (Token::EOF, Span::new(0, 6, 6)),
+        ]
+    );
+}
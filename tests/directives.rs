@@ -0,0 +1,74 @@
+// #error、#warning 产生诊断，#line 改变之后的 __LINE__ 与 __FILE__
+use lexer::lexer::Severity;
+use lexer::preprocessor::{PreprocessError, Preprocessor};
+
+fn spellings(source: &str) -> (Vec<String>, Vec<PreprocessError>) {
+    let mut preprocessor = Preprocessor::new();
+    let (tokens, errors) = preprocessor.preprocess("main.c", source);
+    let spellings = tokens
+        .into_iter()
+        .map(|token| token.spelling)
+        .filter(|spelling| !spelling.is_empty())
+        .collect();
+    (spellings, errors)
+}
+
+#[test]
+fn error_and_warning_report_their_message() {
+    let source =
+        "#if 0\n#error skipped\n#endif\n#warning  deprecated   header\n#error \"bad\" config\n";
+    let (_, errors) = spellings(source);
+    let reported: Vec<(String, Severity, usize)> = errors
+        .iter()
+        .map(|error| (error.to_string(), error.severity(), error.location().row()))
+        .collect();
+    assert_eq!(
+        reported,
+        [
+            (
+                "#warning deprecated header [W0123]".to_string(),
+                Severity::Warning,
+                4
+            ),
+            (
+                "#error \"bad\" config [E0122]".to_string(),
+                Severity::Error,
+                5
+            ),
+        ]
+    );
+}
+
+#[test]
+fn line_changes_line_and_file() {
+    let source = "__LINE__ __FILE__\n#line 100\n__LINE__\n\n__LINE__ __FILE__\n#define NAME \"gen\\\\x.y\"\n#line 7 NAME\n__LINE__ __FILE__\n";
+    let (spellings, errors) = spellings(source);
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(
+        spellings,
+        [
+            "1",
+            "\"main.c\"",
+            "100",
+            "102",
+            "\"main.c\"",
+            "7",
+            "\"gen\\\\x.y\""
+        ]
+    );
+}
+
+#[test]
+fn malformed_line_is_reported() {
+    for source in [
+        "#line\n",
+        "#line 0x10\n",
+        "#line 0\n",
+        "#line 5 name\n",
+        "#line 5 \"a\" 1\n",
+    ] {
+        let (_, errors) = spellings(source);
+        let codes: Vec<&str> = errors.iter().map(PreprocessError::code).collect();
+        assert_eq!(codes, ["E0124"], "{:?}", source);
+    }
+}
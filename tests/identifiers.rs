@@ -0,0 +1,87 @@
+// 标识符中的 Unicode 字符与通用字符名
+use lexer::lexer::{LexError, Lexer, Span, Token};
+use std::borrow::Cow;
+
+fn lex(source: &str) -> (Vec<Token<'_>>, Vec<LexError>) {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    for item in Lexer::new(source) {
+        match item {
+            Ok((token, _)) => tokens.push(token),
+            Err(error) => errors.push(error),
+        }
+    }
+    (tokens, errors)
+}
+
+fn identifier(name: &str) -> Token<'_> {
+    Token::Identifiers(name.into())
+}
+
+#[test]
+fn characters_not_allowed_in_identifiers() {
+    // 紧挨着标识符的字符报告为标识符中不允许的字符，标识符不被拆开
+    let (tokens, errors) = lex("a€b c");
+    assert_eq!(tokens, [identifier("a€b"), identifier("c"), Token::EOF]);
+    assert_eq!(
+        errors,
+        [LexError::InvalidIdentifierCharacter {
+            ch: '€',
+            span: Span::new(0, 1, 4)
+        }]
+    );
+    // 不在标识符中的仍是无法识别的字符
+    let (tokens, errors) = lex("€ x");
+    assert_eq!(tokens, [identifier("x"), Token::EOF]);
+    assert_eq!(
+        errors,
+        [LexError::UnknownCharacter {
+            ch: '€',
+            span: Span::new(0, 0, 3)
+        }]
+    );
+}
+
+#[test]
+fn identifiers_are_normalized_to_nfc() {
+    // 分解形式的 ï 与通用字符名都得到 NFC 形式的拼写，已经是 NFC 的标识符直接借用输入
+    let (tokens, errors) = lex("naïve nai\u{308}ve na\\u00efve");
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(
+        tokens,
+        [
+            identifier("naïve"),
+            identifier("naïve"),
+            identifier("naïve"),
+            Token::EOF
+        ]
+    );
+    let borrowed: Vec<bool> = tokens
+        .iter()
+        .map(|token| matches!(token, Token::Identifiers(Cow::Borrowed(_))))
+        .collect();
+    assert_eq!(borrowed, [true, false, false, false]);
+}
+
+#[test]
+fn universal_character_names() {
+    // 源码、标识符的拼写与错误码
+    let table: [(&str, &str, &[&str]); 8] = [
+        ("\\u00e9t\\u00e9", "été", &[]),
+        ("\\U000000E9", "é", &[]),
+        ("日本語", "日本語", &[]),
+        ("_ü", "_ü", &[]),
+        // A 是基本字符集中的字符，\uD800 是代理项，都不是合法的通用字符名
+        ("a\\u0041", "a", &["E0015"]),
+        ("x\\uD800", "x", &["E0015"]),
+        // 😀 不是 XID_Continue，U+0300 不能出现在标识符开头
+        ("x\\U0001F600", "x😀", &["E0021"]),
+        ("\\u0300x", "\u{300}x", &["E0021"]),
+    ];
+    for (source, name, codes) in table {
+        let (tokens, errors) = lex(source);
+        assert_eq!(tokens, [identifier(name), Token::EOF], "{:?}", source);
+        let reported: Vec<&str> = errors.iter().map(LexError::code).collect();
+        assert_eq!(reported, codes, "{:?}", source);
+    }
+}
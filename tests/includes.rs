@@ -0,0 +1,146 @@
+// #include 的搜索顺序、#pragma once 与保护宏、循环包含
+use lexer::lexer::Token;
+use lexer::preprocessor::{PreprocessError, Preprocessor};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 测试结束时删除的临时目录
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("lexer-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    fn write(&self, name: &str, contents: &str) -> PathBuf {
+        let path = self.0.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// 预处理后剩下的 token 文本，以空格分隔
+fn output(preprocessor: &mut Preprocessor, path: &Path) -> (String, Vec<PreprocessError>) {
+    let (tokens, errors) = preprocessor.preprocess_file(path).unwrap();
+    let text: Vec<String> = tokens
+        .into_iter()
+        .filter(|token| token.token != Token::EOF)
+        .map(|token| token.spelling)
+        .collect();
+    (text.join(" "), errors)
+}
+
+#[test]
+fn search_order() {
+    let dir = TempDir::new("search-order");
+    dir.write("src/a.h", "local_a");
+    dir.write("inc/a.h", "inc_a");
+    dir.write("sys/a.h", "sys_a");
+    dir.write("inc/b.h", "inc_b");
+    dir.write("sys/b.h", "sys_b");
+    dir.write("sys/c.h", "sys_c");
+    dir.write("inc/n.h", "inc_n\n#include_next <n.h>\n");
+    dir.write("sys/n.h", "sys_n");
+    let main = dir.write(
+        "src/main.c",
+        "#include \"a.h\"\n#include <a.h>\n#include \"b.h\"\n#include <c.h>\n#include <n.h>\n#include \"missing.h\"\n",
+    );
+    let mut preprocessor = Preprocessor::new()
+        .with_include_dir(dir.path("inc"))
+        .with_system_include_dir(dir.path("sys"));
+    let (text, errors) = output(&mut preprocessor, &main);
+    // "..." 先搜索当前文件所在目录，<...> 只搜索 -I 与 -isystem，-I 在前
+    assert_eq!(text, "local_a inc_a inc_b sys_c inc_n sys_n");
+    let reported: Vec<(String, usize)> = errors
+        .iter()
+        .map(|error| (error.to_string(), error.location().row()))
+        .collect();
+    assert_eq!(
+        reported,
+        [(
+            "missing.h: No such file or directory [E0113]".to_string(),
+            6
+        )]
+    );
+}
+
+#[test]
+fn once_and_include_guards() {
+    let dir = TempDir::new("once-guards");
+    dir.write("once.h", "#pragma once\nonce\n");
+    dir.write(
+        "guard.h",
+        "#ifndef GUARD_H\n#define GUARD_H\nguard\n#endif\n",
+    );
+    dir.write("open.h", "#ifndef OPEN_H\nopen\n#endif\n");
+    let main = dir.write(
+        "main.c",
+        "#include \"once.h\"\n#include \"guard.h\"\n#include \"open.h\"\n\
+#include \"once.h\"\n#include \"guard.h\"\n#include \"open.h\"\nGUARD_H\n",
+    );
+    let mut preprocessor = Preprocessor::new();
+    let (text, errors) = output(&mut preprocessor, &main);
+    assert!(errors.is_empty(), "{:?}", errors);
+    // 没有定义保护宏的 open.h 每次都会展开
+    assert_eq!(text, "once guard open open");
+    // 第二次包含 once.h 与 guard.h 时不再读取文件
+    let names: Vec<String> = (0..)
+        .map_while(|id| preprocessor.file_name(id))
+        .map(|name| {
+            Path::new(name)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .into()
+        })
+        .collect();
+    assert_eq!(names, ["main.c", "once.h", "guard.h", "open.h", "open.h"]);
+}
+
+#[test]
+fn include_cycles_are_reported() {
+    let dir = TempDir::new("cycles");
+    dir.write("a.h", "a_begin\n#include \"b.h\"\na_end\n");
+    dir.write("b.h", "b\n#include \"a.h\"\n");
+    dir.write("self.h", "#include \"self.h\"\nself\n");
+    let main = dir.write("main.c", "#include \"a.h\"\n#include \"self.h\"\n");
+    let mut preprocessor = Preprocessor::new();
+    let (text, errors) = output(&mut preprocessor, &main);
+    assert_eq!(text, "a_begin b a_end self");
+    let b = dir.path("b.h");
+    let a = dir.path("a.h");
+    let diagnostics: Vec<String> = errors
+        .iter()
+        .map(|error| preprocessor.format_diagnostic(error))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [
+            format!(
+                "In file included from {}:2:2,\n                 from {}:1:2:\n{}:2:2: error: #include nested recursively: a.h [E0115]",
+                a.display(),
+                main.display(),
+                b.display()
+            ),
+            format!(
+                "In file included from {}:2:2:\n{}:1:2: error: #include nested recursively: self.h [E0115]",
+                main.display(),
+                dir.path("self.h").display()
+            ),
+        ]
+    );
+}
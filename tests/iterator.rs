@@ -0,0 +1,61 @@
+// Lexer 按需识别 token：提前停止时不会读完整个输入，EOF 之后返回 None
+use lexer::lexer::{Lexer, Token};
+use std::cell::Cell;
+use std::io::{self, BufReader, Read};
+use std::rc::Rc;
+
+// 记录已经被读取的字节数
+struct Counting<'a> {
+    data: &'a [u8],
+    read: Rc<Cell<usize>>,
+}
+
+impl Read for Counting<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = self.read.get();
+        let len = buf.len().min(self.data.len() - start);
+        buf[..len].copy_from_slice(&self.data[start..start + len]);
+        self.read.set(start + len);
+        Ok(len)
+    }
+}
+
+#[test]
+fn stopping_early_reads_only_what_is_needed() {
+    let source = "first second third ".repeat(10_000);
+    let read = Rc::new(Cell::new(0));
+    let reader = BufReader::with_capacity(
+        64,
+        Counting {
+            data: source.as_bytes(),
+            read: Rc::clone(&read),
+        },
+    );
+    let names: Vec<Token> = Lexer::from_reader(reader)
+        .take(2)
+        .map(|item| item.unwrap().0)
+        .collect();
+    assert_eq!(
+        names,
+        [
+            Token::Identifiers("first".into()),
+            Token::Identifiers("second".into())
+        ]
+    );
+    assert!(read.get() <= 128, "{}", read.get());
+}
+
+#[test]
+fn next_token_returns_none_after_eof() {
+    let mut lexer = Lexer::new("a @ b");
+    assert_eq!(
+        lexer.next_token().unwrap().unwrap().0,
+        Token::Identifiers("a".into())
+    );
+    // 错误与 token 按出现顺序交替返回
+    assert_eq!(lexer.next_token().unwrap().unwrap_err().code(), "E0001");
+    let rest: Vec<Token> = lexer.by_ref().map(|item| item.unwrap().0).collect();
+    assert_eq!(rest, [Token::Identifiers("b".into()), Token::EOF]);
+    assert!(lexer.next_token().is_none());
+    assert!(lexer.next().is_none());
+}
@@ -0,0 +1,77 @@
+// 关键字随语言标准变化，默认区分大小写，可以通过兼容开关忽略大小写
+use lexer::lexer::{Keyword, LanguageStandard, Lexer, Token};
+
+// 按给定标准识别单个单词得到的 token
+fn lex_word(word: &str, standard: LanguageStandard) -> Token<'static> {
+    let tokens: Vec<Token> = Lexer::new(word)
+        .with_standard(standard)
+        .map(|item| item.unwrap().0.into_owned())
+        .collect();
+    assert_eq!(tokens.len(), 2, "{:?}", word);
+    tokens[0].clone()
+}
+
+#[test]
+fn keywords_depend_on_the_standard() {
+    use LanguageStandard::*;
+    // 单词、对应的关键字与引入它的标准，之前的标准中是普通标识符
+    let table = [
+        ("int", Keyword::Int, C89),
+        ("while", Keyword::While, C89),
+        ("inline", Keyword::Inline, C99),
+        ("restrict", Keyword::Restrict, C99),
+        ("_Bool", Keyword::Bool, C99),
+        ("_Alignas", Keyword::Alignas, C11),
+        ("_Thread_local", Keyword::ThreadLocal, C11),
+        ("bool", Keyword::Bool, C23),
+        ("nullptr", Keyword::Nullptr, C23),
+        ("true", Keyword::True, C23),
+        ("alignas", Keyword::Alignas, C23),
+        ("typeof", Keyword::Typeof, C23),
+        ("_BitInt", Keyword::BitInt, C23),
+    ];
+    for (word, keyword, since) in table {
+        for standard in [C89, C99, C11, C17, C23] {
+            let expected = if standard >= since {
+                Token::Keywords(keyword.clone())
+            } else {
+                Token::Identifiers(word.into())
+            };
+            assert_eq!(
+                lex_word(word, standard),
+                expected,
+                "{:?} in {:?}",
+                word,
+                standard
+            );
+        }
+    }
+}
+
+#[test]
+fn keywords_are_case_sensitive_by_default() {
+    let words = [
+        ("While", Keyword::While),
+        ("INT", Keyword::Int),
+        ("Return", Keyword::Return),
+        ("SIZEOF", Keyword::Sizeof),
+    ];
+    for (word, keyword) in words {
+        assert_eq!(
+            lex_word(word, LanguageStandard::C17),
+            Token::Identifiers(word.into())
+        );
+        // 开启兼容开关后忽略大小写，仍然遵循语言标准
+        let tokens: Vec<Token> = Lexer::new(word)
+            .with_case_insensitive_keywords(true)
+            .map(|item| item.unwrap().0)
+            .collect();
+        assert_eq!(tokens, [Token::Keywords(keyword), Token::EOF], "{:?}", word);
+    }
+    let tokens: Vec<Token> = Lexer::new("Inline")
+        .with_standard(LanguageStandard::C89)
+        .with_case_insensitive_keywords(true)
+        .map(|item| item.unwrap().0)
+        .collect();
+    assert_eq!(tokens, [Token::Identifiers("Inline".into()), Token::EOF]);
+}
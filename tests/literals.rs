@@ -0,0 +1,326 @@
+// 字符常量、字符串、整数与浮点常量：转义的解码、编码前缀、后缀、类型与数字分隔符
+use lexer::lexer::{
+    CharLiteral, Encoding, FloatConstant, FloatType, IntegerConstant, IntegerSuffix, IntegerType,
+    LanguageStandard, LexError, Lexer, Number, Radix, Severity, Span, StringLiteral, Token,
+};
+
+fn lex(lexer: Lexer<'_>) -> (Vec<Token<'_>>, Vec<LexError>) {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    for item in lexer {
+        match item {
+            Ok((token, _)) => tokens.push(token),
+            Err(error) => errors.push(error),
+        }
+    }
+    assert_eq!(tokens.pop(), Some(Token::EOF));
+    (tokens, errors)
+}
+
+fn char_constant(encoding: Encoding, raw: &str, value: i64) -> Token<'_> {
+    Token::CharConstant(CharLiteral {
+        encoding,
+        raw: raw.into(),
+        value,
+    })
+}
+
+#[test]
+fn char_constants_decode_escapes() {
+    let table = [
+        ("'a'", 97),
+        ("'\\n'", 10),
+        ("'\\''", 39),
+        ("'\\x41'", 65),
+        ("'\\101'", 65),
+        ("'\\0'", 0),
+        // 普通字符常量是 char，\377 与 \xff 符号扩展为 -1
+        ("'\\377'", -1),
+        ("'\\xff'", -1),
+    ];
+    for (source, value) in table {
+        let (tokens, errors) = lex(Lexer::new(source));
+        assert!(errors.is_empty(), "{:?}: {:?}", source, errors);
+        assert_eq!(tokens, [char_constant(Encoding::Plain, source, value)]);
+    }
+}
+
+#[test]
+fn empty_and_multi_char_constants() {
+    // '' 是错误，只留下一个 Error token
+    let (tokens, errors) = lex(Lexer::new("''"));
+    assert_eq!(tokens, [Token::Error]);
+    assert_eq!(errors, [LexError::EmptyCharConstant(Span::new(0, 0, 2))]);
+    assert_eq!(errors[0].severity(), Severity::Error);
+
+    // 多字符常量只是警告，值按 GCC 的方式逐字节拼接
+    let (tokens, errors) = lex(Lexer::new("'ab'"));
+    assert_eq!(tokens, [char_constant(Encoding::Plain, "'ab'", 0x6162)]);
+    assert_eq!(errors, [LexError::MultiCharConstant(Span::new(0, 0, 4))]);
+    assert_eq!(errors[0].severity(), Severity::Warning);
+}
+
+#[test]
+fn strings_keep_raw_and_cooked_forms() {
+    let table = [
+        ("\"a\\\"b\"", "a\"b"),
+        ("\"\\x41\\101\\n\"", "AA\n"),
+        ("\"caf\\u00e9\"", "café"),
+        ("\"\\\\\"", "\\"),
+        ("\"\"", ""),
+    ];
+    for (source, cooked) in table {
+        let (tokens, errors) = lex(Lexer::new(source));
+        assert!(errors.is_empty(), "{:?}: {:?}", source, errors);
+        match &tokens[..] {
+            [Token::Strings(literal)] => {
+                assert_eq!(literal.raw, source);
+                assert_eq!(literal.to_string_lossy(), cooked, "{:?}", source);
+            }
+            other => panic!("{:?}: {:?}", source, other),
+        }
+    }
+
+    // 窄字符串中的 \u00e9 编码为 UTF-8 字节
+    let (tokens, _) = lex(Lexer::new("\"\\u00e9\""));
+    assert_eq!(
+        tokens,
+        [Token::Strings(StringLiteral {
+            encoding: Encoding::Plain,
+            raw: "\"\\u00e9\"".into(),
+            value: vec![0xc3, 0xa9],
+        })]
+    );
+}
+
+#[test]
+fn invalid_escapes_in_strings() {
+    let (tokens, errors) = lex(Lexer::new("\"\\q\\x\""));
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(
+        errors,
+        [
+            LexError::UnknownEscape {
+                ch: 'q',
+                span: Span::new(0, 1, 3)
+            },
+            LexError::MissingHexEscapeDigits(Span::new(0, 3, 5)),
+        ]
+    );
+}
+
+#[test]
+fn encoding_prefixes() {
+    let strings = [
+        ("\"é\"", Encoding::Plain, vec![0xc3, 0xa9]),
+        ("u8\"é\"", Encoding::Utf8, vec![0xc3, 0xa9]),
+        ("u\"é😀\"", Encoding::Utf16, vec![0xe9, 0xd83d, 0xde00]),
+        ("U\"é😀\"", Encoding::Utf32, vec![0xe9, 0x1f600]),
+        ("L\"\\U0001F600\"", Encoding::Wide, vec![0x1f600]),
+    ];
+    for (source, encoding, value) in strings {
+        let (tokens, errors) = lex(Lexer::new(source));
+        assert!(errors.is_empty(), "{:?}: {:?}", source, errors);
+        assert_eq!(
+            tokens,
+            [Token::Strings(StringLiteral {
+                encoding,
+                raw: source.into(),
+                value,
+            })]
+        );
+    }
+    let chars = [
+        ("L'a'", Encoding::Wide, 97),
+        ("u'\\u00e9'", Encoding::Utf16, 0xe9),
+        ("U'😀'", Encoding::Utf32, 0x1f600),
+        ("u8'a'", Encoding::Utf8, 97),
+    ];
+    for (source, encoding, value) in chars {
+        let (tokens, errors) = lex(Lexer::new(source));
+        assert!(errors.is_empty(), "{:?}: {:?}", source, errors);
+        assert_eq!(tokens, [char_constant(encoding, source, value)]);
+    }
+
+    // 编码单元装不下的转义超出范围
+    let (_, errors) = lex(Lexer::new("u8'\\xfff' u'\\x10000'"));
+    let codes: Vec<&str> = errors.iter().map(LexError::code).collect();
+    assert_eq!(codes, ["E0014", "E0014"]);
+
+    // 前缀后面不是引号时仍是普通的标识符
+    let (tokens, _) = lex(Lexer::new("u8 L x"));
+    assert_eq!(
+        tokens,
+        [
+            Token::Identifiers("u8".into()),
+            Token::Identifiers("L".into()),
+            Token::Identifiers("x".into())
+        ]
+    );
+}
+
+fn integer(value: u64, radix: Radix, suffix: IntegerSuffix, ty: IntegerType) -> Token<'static> {
+    Token::Numbers(Number::Integer(IntegerConstant {
+        value,
+        radix,
+        suffix,
+        ty,
+    }))
+}
+
+#[test]
+fn integer_suffixes_and_types() {
+    use IntegerSuffix as S;
+    use IntegerType as T;
+    let table = [
+        ("10", 10, Radix::Decimal, S::None, T::Int),
+        ("10UL", 10, Radix::Decimal, S::UnsignedLong, T::UnsignedLong),
+        ("10lu", 10, Radix::Decimal, S::UnsignedLong, T::UnsignedLong),
+        ("10ll", 10, Radix::Decimal, S::LongLong, T::LongLong),
+        (
+            "10uLL",
+            10,
+            Radix::Decimal,
+            S::UnsignedLongLong,
+            T::UnsignedLongLong,
+        ),
+        ("017u", 15, Radix::Octal, S::Unsigned, T::UnsignedInt),
+        // 十进制常量只选有符号类型，8进制与16进制常量也可以选无符号类型
+        ("2147483648", 1 << 31, Radix::Decimal, S::None, T::Long),
+        (
+            "0x80000000",
+            1 << 31,
+            Radix::Hexadecimal,
+            S::None,
+            T::UnsignedInt,
+        ),
+        (
+            "0xFFFFFFFFFFFFFFFF",
+            u64::MAX,
+            Radix::Hexadecimal,
+            S::None,
+            T::UnsignedLong,
+        ),
+        (
+            "4294967296u",
+            1 << 32,
+            Radix::Decimal,
+            S::Unsigned,
+            T::UnsignedLong,
+        ),
+    ];
+    for (source, value, radix, suffix, ty) in table {
+        let (tokens, errors) = lex(Lexer::new(source));
+        assert!(errors.is_empty(), "{:?}: {:?}", source, errors);
+        assert_eq!(tokens, [integer(value, radix, suffix, ty)], "{:?}", source);
+    }
+}
+
+#[test]
+fn invalid_integer_constants() {
+    // lL 与 Ll 的大小写不一致，不是合法的后缀
+    let (tokens, errors) = lex(Lexer::new("1lL 2uu 3Ll"));
+    assert_eq!(tokens, [Token::Error, Token::Error, Token::Error]);
+    assert_eq!(
+        errors,
+        [
+            LexError::InvalidIntegerSuffix(Span::new(0, 1, 3)),
+            LexError::InvalidIntegerSuffix(Span::new(0, 5, 7)),
+            LexError::InvalidIntegerSuffix(Span::new(0, 9, 11)),
+        ]
+    );
+
+    // 超出 long long 的十进制常量按 unsigned long long 处理并警告，超出 64 位则是错误
+    let (tokens, errors) = lex(Lexer::new("9223372036854775808 18446744073709551616"));
+    assert_eq!(
+        tokens,
+        [
+            integer(
+                1 << 63,
+                Radix::Decimal,
+                IntegerSuffix::None,
+                IntegerType::UnsignedLongLong
+            ),
+            Token::Error
+        ]
+    );
+    let codes: Vec<&str> = errors.iter().map(LexError::code).collect();
+    assert_eq!(codes, ["W0017", "E0007"]);
+}
+
+#[test]
+fn float_constants_and_suffixes() {
+    let table = [
+        ("1.0", 1.0, Radix::Decimal, FloatType::Double),
+        ("1.0f", 1.0, Radix::Decimal, FloatType::Float),
+        ("2.5L", 2.5, Radix::Decimal, FloatType::LongDouble),
+        (".5", 0.5, Radix::Decimal, FloatType::Double),
+        ("1e3", 1000.0, Radix::Decimal, FloatType::Double),
+        ("0x1.8p3", 12.0, Radix::Hexadecimal, FloatType::Double),
+        ("0x.8p-1F", 0.25, Radix::Hexadecimal, FloatType::Float),
+        ("0X1P+2l", 4.0, Radix::Hexadecimal, FloatType::LongDouble),
+    ];
+    for (source, value, radix, ty) in table {
+        let (tokens, errors) = lex(Lexer::new(source));
+        assert!(errors.is_empty(), "{:?}: {:?}", source, errors);
+        assert_eq!(
+            tokens,
+            [Token::Numbers(Number::Float(FloatConstant {
+                value,
+                radix,
+                ty
+            }))],
+            "{:?}",
+            source
+        );
+    }
+
+    // 16进制浮点数必须有 p 指数，f 与 l 之外的后缀不合法
+    let (tokens, errors) = lex(Lexer::new("0x1.8 1.0q 1e+"));
+    assert_eq!(tokens, [Token::Error, Token::Error, Token::Error]);
+    let codes: Vec<&str> = errors.iter().map(LexError::code).collect();
+    assert_eq!(codes, ["E0018", "E0019", "E0003"]);
+}
+
+// C23 中识别的整数常量的值
+fn c23_values(source: &str) -> (Vec<u64>, Vec<LexError>) {
+    let (tokens, errors) = lex(Lexer::new(source).with_standard(LanguageStandard::C23));
+    let values = tokens
+        .into_iter()
+        .map(|token| match token {
+            Token::Numbers(Number::Integer(integer)) => integer.value,
+            other => panic!("{:?}: {:?}", source, other),
+        })
+        .collect();
+    (values, errors)
+}
+
+#[test]
+fn digit_separators() {
+    let (values, errors) = c23_values("1'000'000 0b1010'0101 0x1'f 07'7");
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(values, [1_000_000, 0b1010_0101, 0x1f, 0o77]);
+
+    // 分隔符不在两个数字之间时报错，但仍跳过它继续识别
+    let (values, errors) = c23_values("1''0 1' 0x'1");
+    assert_eq!(values, [10, 1, 1]);
+    assert_eq!(
+        errors,
+        [
+            LexError::InvalidDigitSeparator(Span::new(0, 1, 2)),
+            LexError::InvalidDigitSeparator(Span::new(0, 2, 3)),
+            LexError::InvalidDigitSeparator(Span::new(0, 6, 7)),
+            LexError::InvalidDigitSeparator(Span::new(0, 10, 11)),
+        ]
+    );
+
+    // C23 之前 ' 仍开始一个字符常量
+    let (tokens, _) = lex(Lexer::new("1'2'"));
+    assert_eq!(
+        tokens,
+        [
+            integer(1, Radix::Decimal, IntegerSuffix::None, IntegerType::Int),
+            char_constant(Encoding::Plain, "'2'", 50)
+        ]
+    );
+}
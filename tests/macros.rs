@@ -0,0 +1,123 @@
+// 宏展开：对象式与函数式宏、# 与 ##、展开过程中的递归
+use lexer::lexer::{Encoding, StringLiteral, Token};
+use lexer::preprocessor::{PpToken, PreprocessError, Preprocessor};
+
+fn preprocess(source: &str) -> (Vec<PpToken>, Vec<PreprocessError>) {
+    let mut preprocessor = Preprocessor::new();
+    let (mut tokens, errors) = preprocessor.preprocess("main.c", source);
+    assert_eq!(tokens.pop().map(|token| token.token), Some(Token::EOF));
+    (tokens, errors)
+}
+
+#[test]
+fn stringify_decodes_string_and_char_arguments() {
+    let (tokens, errors) = preprocess("#define S(x) #x\nS(\"a\") S('\\n') S(p = \"\\\\\" 'x')\n");
+    assert!(errors.is_empty(), "{:?}", errors);
+    let literals: Vec<(&str, String)> = tokens
+        .iter()
+        .map(|token| match &token.token {
+            Token::Strings(StringLiteral {
+                encoding: Encoding::Plain,
+                value,
+                ..
+            }) => (
+                token.spelling.as_str(),
+                value.iter().map(|&c| char::from_u32(c).unwrap()).collect(),
+            ),
+            other => panic!("{:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        literals,
+        [
+            (r#""\"a\"""#, r#""a""#.to_string()),
+            (r#""'\\n'""#, r"'\n'".to_string()),
+            (r#""p = \"\\\\\" 'x'""#, r#"p = "\\" 'x'"#.to_string()),
+        ]
+    );
+}
+
+// 展开结果的 token 文本，以空格分隔
+fn text(tokens: &[PpToken]) -> String {
+    let spellings: Vec<&str> = tokens.iter().map(|token| token.spelling.as_str()).collect();
+    spellings.join(" ")
+}
+
+#[test]
+fn object_and_function_like_macros_expand() {
+    let source =
+        "#define N 10\n#define TWICE(x) ((x) * 2)\n#define ADD(a, b) a + b\n#define F (N)\n\
+TWICE(N) ADD(1, TWICE(2)) F TWICE (3) TWICE ADD((1, 2), 3)\n";
+    let (tokens, errors) = preprocess(source);
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(
+        text(&tokens),
+        "( ( 10 ) * 2 ) 1 + ( ( 2 ) * 2 ) ( 10 ) ( ( 3 ) * 2 ) TWICE ( 1 , 2 ) + 3"
+    );
+}
+
+#[test]
+fn stringify_and_paste() {
+    let source =
+        "#define STR(x) #x\n#define XSTR(x) STR(x)\n#define CAT(a, b) a ## b\n#define V 42\n\
+STR(  a   +\tb ) STR(V) XSTR(V) CAT(x, 1) CAT(V, ) CAT(<, <=) CAT(+, -)\n";
+    let (tokens, errors) = preprocess(source);
+    assert_eq!(text(&tokens), "\"a + b\" \"V\" \"42\" x1 42 <<= + -");
+    let codes: Vec<&str> = errors.iter().map(PreprocessError::code).collect();
+    assert_eq!(codes, ["E0110"]);
+    assert_eq!(
+        errors[0].to_string(),
+        "pasting \"+-\" does not give a valid preprocessing token [E0110]"
+    );
+}
+
+#[test]
+fn hide_set_stops_recursion() {
+    let source = "#define foo foo a\n#define x y\n#define y x\n#define f(n) n + f(n)\n\
+#define g f\n#define AB(p) p\nfoo x y f(1) g(2)(3) AB(AB)(4)\n";
+    let (tokens, errors) = preprocess(source);
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(
+        text(&tokens),
+        "foo a x y 1 + f ( 1 ) 2 + f ( 2 ) ( 3 ) AB ( 4 )"
+    );
+}
+
+#[test]
+fn macro_errors_are_reported() {
+    let source = "#define A 1\n#define A 2\n#define P(x, x) x\n#define H(x) #y\n#define Q(x) x\nQ(1, 2) Q(\n";
+    let (_, errors) = preprocess(source);
+    let codes: Vec<&str> = errors.iter().map(PreprocessError::code).collect();
+    assert_eq!(codes, ["W0102", "E0104", "E0105", "E0109", "E0108"]);
+}
+
+#[test]
+fn define_checks_the_body_like_define_directive() {
+    let mut preprocessor = Preprocessor::new();
+    let codes = |errors: Vec<PreprocessError>| -> Vec<&'static str> {
+        errors.iter().map(PreprocessError::code).collect()
+    };
+    assert_eq!(codes(preprocessor.define("X", "a ##")), ["E0106"]);
+    assert_eq!(codes(preprocessor.define("S", "\"abc")), ["E0009"]);
+    assert_eq!(codes(preprocessor.define("H(x)", "#y")), ["E0105"]);
+    assert!(preprocessor.define("CAT(a, b)", "a ## b").is_empty());
+    assert!(!preprocessor.is_defined("X") && !preprocessor.is_defined("S"));
+    let (tokens, errors) = preprocessor.preprocess("main.c", "X S CAT(x, 1)\n");
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(text(&tokens[..tokens.len() - 1]), "X S x1");
+}
+
+#[test]
+fn wrong_argument_count_keeps_the_invocation() {
+    let source = "#define A(x) x\n#define P(x, y) x y\n#define B 2\nA(1, B) P(1) A(3)\n";
+    let (tokens, errors) = preprocess(source);
+    assert_eq!(text(&tokens), "A ( 1 , 2 ) P ( 1 ) 3");
+    let reported: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        reported,
+        [
+            "macro \"A\" requires 1 arguments, but 2 given [E0109]",
+            "macro \"P\" requires 2 arguments, but 1 given [E0109]",
+        ]
+    );
+}
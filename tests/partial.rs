@@ -0,0 +1,144 @@
+// 部分预处理：只处理由已知的宏决定的条件编译，其余源码原样输出
+use lexer::preprocessor::{PreprocessError, Preprocessor};
+
+// 定义 FOO、取消定义 BAR 之后做部分预处理
+fn partial(source: &str) -> (String, Vec<PreprocessError>) {
+    let mut preprocessor = Preprocessor::new();
+    preprocessor.define("FOO", "1");
+    preprocessor.undefine("BAR");
+    preprocessor.preprocess_partial("main.c", source)
+}
+
+#[test]
+fn known_conditions_are_resolved() {
+    let table = [
+        ("#ifdef FOO\na\n#else\nb\n#endif\n", "a\n"),
+        ("#if defined(BAR) || FOO == 2\na\n#else\nb\n#endif\n", "b\n"),
+        ("#ifndef BAR\n#if FOO\na\n#endif\n#endif\nc\n", "a\nc\n"),
+        ("#if BAR\na\n#elif FOO\nb\n#elif X\nc\n#endif\n", "b\n"),
+    ];
+    for (source, expected) in table {
+        let (output, errors) = partial(source);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(output, expected, "{:?}", source);
+    }
+}
+
+#[test]
+fn elif_is_rewritten() {
+    let table = [
+        // 条件未知的分支之后第一个为真的 #elif 改写为 #else，其后的分支删除
+        (
+            "#if X\na\n#elif defined(FOO)\nb\n#elif Y\nc\n#else\nd\n#endif\n",
+            "#if X\na\n#else\nb\n#endif\n",
+        ),
+        // 为假的分支删除，之后条件未知的 #elif 成为新的 #if
+        (
+            "#if BAR\na\n#elif X\nb\n#else\nc\n#endif\n",
+            "#if X\nb\n#else\nc\n#endif\n",
+        ),
+        (
+            "#ifdef BAR\na\n#elifdef X\nb\n#endif\n",
+            "#ifdef X\nb\n#endif\n",
+        ),
+        (
+            "#ifdef BAR\na\n#elifndef X\nb\n#endif\n",
+            "#ifndef X\nb\n#endif\n",
+        ),
+        // 条件为假的 #elif 直接删除
+        (
+            "#if X\na\n#elif BAR\nb\n#else\nc\n#endif\n",
+            "#if X\na\n#else\nc\n#endif\n",
+        ),
+    ];
+    for (source, expected) in table {
+        let (output, errors) = partial(source);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(output, expected, "{:?}", source);
+    }
+}
+
+#[test]
+fn unknown_macros_and_other_directives_are_kept() {
+    let source =
+        "#include <stdio.h>\n#define LOCAL FOO + 1\n#ifdef UNKNOWN\nint x = FOO;\n#endif\n\
+#if UNKNOWN > 1 && defined(FOO)\nLOCAL BAR\n#endif\n#undef FOO\n#pragma once\n";
+    let (output, errors) = partial(source);
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(output, source);
+}
+
+#[test]
+fn directives_with_trailing_comments() {
+    let table = [
+        (
+            "#ifdef FOO // enabled\na\n#else /* never */\nb\n#endif /* FOO */\n",
+            "a\n",
+        ),
+        (
+            "#if X /* keep */\na\n#elif defined(FOO) // why\nb\n#endif // end\n",
+            "#if X /* keep */\na\n#else\nb\n#endif // end\n",
+        ),
+        (
+            "#if BAR\na\n#elif X /* multi\nline */\nb\n#endif\n",
+            "#if X /* multi\nline */\nb\n#endif\n",
+        ),
+    ];
+    for (source, expected) in table {
+        let (output, errors) = partial(source);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(output, expected, "{:?}", source);
+    }
+}
+
+#[test]
+fn unbalanced_conditionals_are_reported() {
+    let (output, errors) = partial("#endif\n#ifdef FOO\na\n");
+    assert_eq!(output, "#endif\na\n");
+    let reported: Vec<(String, usize)> = errors
+        .iter()
+        .map(|error| (error.to_string(), error.location().row()))
+        .collect();
+    assert_eq!(
+        reported,
+        [
+            ("#endif without #if [E0117]".to_string(), 1),
+            ("unterminated #ifdef [E0116]".to_string(), 2),
+        ]
+    );
+}
+
+#[test]
+fn comments_and_splices_before_directives_are_kept() {
+    let table = [
+        ("/* c\n */ #ifdef FOO\na\n#endif\n", "/* c\n */a\n"),
+        (
+            "x /* c */\n  /* d */ #ifdef BAR\na\n#endif\ny\n",
+            "x /* c */\n  /* d */y\n",
+        ),
+        ("a\n  \\\n#ifdef FOO\nb\n#endif\n", "a\nb\n"),
+        (
+            "a // c \\\n still comment\n#ifdef FOO\nb\n#endif\n",
+            "a // c \\\n still comment\nb\n",
+        ),
+        (
+            "/* c\n */ #if X\na\n#elif defined(FOO)\nb\n#endif\n",
+            "/* c\n */ #if X\na\n#else\nb\n#endif\n",
+        ),
+    ];
+    for (source, expected) in table {
+        let (output, errors) = partial(source);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(output, expected, "{:?}", source);
+    }
+}
+
+#[test]
+fn function_like_macros_from_define() {
+    let mut preprocessor = Preprocessor::new();
+    assert!(preprocessor.define("F(x)", "x + 1").is_empty());
+    let source = "#if F(2) == 3\na\n#else\nb\n#endif\nF(4)\n";
+    let (output, errors) = preprocessor.preprocess_partial("main.c", source);
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(output, "a\nF(4)\n");
+}
@@ -0,0 +1,242 @@
+// C17 6.4.6 中的每个标点符号都应按最长匹配识别为恰好一个 token
+use lexer::lexer::{classify_operators, Delimiter, Language, Lexer, Operator, Span, Token};
+
+fn operator(operator: Operator) -> Token<'static> {
+    Token::Operators(operator)
+}
+
+fn delimiter(delimiter: Delimiter) -> Token<'static> {
+    Token::Delimiters(delimiter)
+}
+
+// 两侧放上标识符，使 # 不位于行首
+fn lex_between_identifiers(spelling: &str) -> Vec<(Token<'_>, Span)> {
+    let source = format!("x {} y", spelling);
+    let tokens: Vec<(Token, Span)> = Lexer::new(&source)
+        .map(|item| item.map(|(token, span)| (token.into_owned(), span)))
+        .collect::<Result<_, _>>()
+        .unwrap_or_else(|error| panic!("{:?} failed to lex: {}", spelling, error));
+    assert_eq!(
+        tokens.first().map(|(token, _)| token),
+        Some(&Token::Identifiers("x".into()))
+    );
+    assert_eq!(tokens.last().map(|(token, _)| token), Some(&Token::EOF));
+    let inner = tokens.len() - 2;
+    assert_eq!(
+        tokens[inner].0,
+        Token::Identifiers("y".into()),
+        "{:?}",
+        spelling
+    );
+    tokens[1..inner].to_vec()
+}
+
+#[test]
+fn every_punctuator_is_a_single_token() {
+    let table = [
+        ("[", delimiter(Delimiter::LeftBracket)),
+        ("]", delimiter(Delimiter::RightBracket)),
+        ("(", delimiter(Delimiter::LeftParenthesis)),
+        (")", delimiter(Delimiter::RightParenthesis)),
+        ("{", delimiter(Delimiter::LeftBrace)),
+        ("}", delimiter(Delimiter::RightBrace)),
+        (".", operator(Operator::MemberAccess)),
+        ("->", operator(Operator::PointerMemberAccess)),
+        ("++", operator(Operator::Increment)),
+        ("--", operator(Operator::Decrement)),
+        ("&", operator(Operator::Amp)),
+        ("*", operator(Operator::Star)),
+        ("+", operator(Operator::Plus)),
+        ("-", operator(Operator::Minus)),
+        ("~", operator(Operator::BitwiseNot)),
+        ("!", operator(Operator::LogicalNot)),
+        ("/", operator(Operator::Divide)),
+        ("%", operator(Operator::Modulus)),
+        ("<<", operator(Operator::LeftShift)),
+        (">>", operator(Operator::RightShift)),
+        ("<", operator(Operator::LessThan)),
+        (">", operator(Operator::GreaterThan)),
+        ("<=", operator(Operator::LessThanOrEqual)),
+        (">=", operator(Operator::GreaterThanOrEqual)),
+        ("==", operator(Operator::Equal)),
+        ("!=", operator(Operator::NotEqual)),
+        ("^", operator(Operator::BitwiseXor)),
+        ("|", operator(Operator::BitwiseOr)),
+        ("&&", operator(Operator::LogicalAnd)),
+        ("||", operator(Operator::LogicalOr)),
+        ("?", delimiter(Delimiter::ConditionalOperator)),
+        (":", delimiter(Delimiter::ConditionalSeparator)),
+        (";", delimiter(Delimiter::Semicolon)),
+        ("...", delimiter(Delimiter::Ellipsis)),
+        ("=", operator(Operator::Assign)),
+        ("*=", operator(Operator::MultiplyAssign)),
+        ("/=", operator(Operator::DivideAssign)),
+        ("%=", operator(Operator::ModulusAssign)),
+        ("+=", operator(Operator::AddAssign)),
+        ("-=", operator(Operator::SubtractAssign)),
+        ("<<=", operator(Operator::LeftShiftAssign)),
+        (">>=", operator(Operator::RightShiftAssign)),
+        ("&=", operator(Operator::BitwiseAndAssign)),
+        ("^=", operator(Operator::BitwiseXorAssign)),
+        ("|=", operator(Operator::BitwiseOrAssign)),
+        (",", delimiter(Delimiter::Comma)),
+        ("#", delimiter(Delimiter::Hash)),
+        ("##", delimiter(Delimiter::HashHash)),
+        ("<:", delimiter(Delimiter::LeftBracket)),
+        (":>", delimiter(Delimiter::RightBracket)),
+        ("<%", delimiter(Delimiter::LeftBrace)),
+        ("%>", delimiter(Delimiter::RightBrace)),
+        ("%:", delimiter(Delimiter::Hash)),
+        ("%:%:", delimiter(Delimiter::HashHash)),
+    ];
+    for (spelling, expected) in table {
+        let tokens = lex_between_identifiers(spelling);
+        assert_eq!(tokens.len(), 1, "{:?} lexed as {:?}", spelling, tokens);
+        let (token, span) = &tokens[0];
+        assert_eq!(token, &expected, "{:?}", spelling);
+        assert_eq!(
+            (span.start, span.end),
+            (2, 2 + spelling.len()),
+            "{:?}",
+            spelling
+        );
+    }
+}
+
+#[test]
+fn adjacent_punctuators_use_maximal_munch() {
+    let table: [(&str, &[&str]); 16] = [
+        ("+++", &["++", "+"]),
+        ("+++++", &["++", "++", "+"]),
+        ("-->", &["--", ">"]),
+        ("->>", &["->", ">"]),
+        ("<<<=", &["<<", "<="]),
+        (">>>=", &[">>", ">="]),
+        ("..", &[".", "."]),
+        ("....", &["...", "."]),
+        ("&&&", &["&&", "&"]),
+        ("|||", &["||", "|"]),
+        ("===", &["==", "="]),
+        ("!==", &["!=", "="]),
+        ("###", &["##", "#"]),
+        ("%:%", &["%:", "%"]),
+        ("%:%:%:", &["%:%:", "%:"]),
+        ("<::>", &["<:", ":>"]),
+    ];
+    for (input, expected) in table {
+        let source = format!("x {} y", input);
+        let spellings: Vec<&str> = lex_between_identifiers(input)
+            .iter()
+            .map(|(_, span)| span.source_text(&source))
+            .collect();
+        assert_eq!(spellings, expected, "{:?}", input);
+    }
+}
+
+// C++ 新增的标点符号，以及 <:: 不按双字符组识别的特殊规则
+#[test]
+fn cpp_punctuators_use_maximal_munch() {
+    let table: [(&str, &[&str]); 8] = [
+        ("a::b", &["a", "::", "b"]),
+        ("a.*b", &["a", ".*", "b"]),
+        ("a->*b", &["a", "->*", "b"]),
+        ("a<=>b", &["a", "<=>", "b"]),
+        ("a<::b", &["a", "<", "::", "b"]),
+        ("a<:::b", &["a", "<:", "::", "b"]),
+        ("a<::>b", &["a", "<:", ":>", "b"]),
+        ("a>>=b", &["a", ">>=", "b"]),
+    ];
+    for (input, expected) in table {
+        let spellings: Vec<&str> = Lexer::new(input)
+            .with_language(Language::Cpp)
+            .map(|item| item.unwrap_or_else(|error| panic!("{:?}: {}", input, error)))
+            .filter(|(token, _)| *token != Token::EOF)
+            .map(|(_, span)| span.source_text(input))
+            .collect();
+        assert_eq!(spellings, expected, "{:?}", input);
+    }
+    let (token, span) = Lexer::new(">>=")
+        .with_language(Language::Cpp)
+        .next()
+        .unwrap()
+        .unwrap();
+    let (first, rest) = token.split_greater_than(span).unwrap();
+    assert_eq!(first, (operator(Operator::GreaterThan), Span::new(0, 0, 1)));
+    assert_eq!(
+        rest,
+        (operator(Operator::GreaterThanOrEqual), Span::new(0, 1, 3))
+    );
+}
+
+// 词法分析的输出与上下文无关，一元还是二元由 classify_operators 按前面的 token 与类型名决定
+#[test]
+fn classify_operators_uses_previous_token() {
+    let table: [(&str, &[Operator]); 22] = [
+        ("return -1;", &[Operator::Negate]),
+        ("a - -1", &[Operator::Subtract, Operator::Negate]),
+        ("x * *p", &[Operator::Multiply, Operator::Dereference]),
+        ("f(&x) & y[0]", &[Operator::AddressOf, Operator::BitwiseAnd]),
+        ("&x", &[Operator::AddressOf]),
+        ("a & b", &[Operator::BitwiseAnd]),
+        ("a++ - b", &[Operator::Increment, Operator::Subtract]),
+        ("++*p", &[Operator::Increment, Operator::Dereference]),
+        ("+1 + 2", &[Operator::UnaryPlus, Operator::Add]),
+        // 括号中只有类型名时是类型转换，之后的运算符是一元的
+        ("(int)*p", &[Operator::Dereference]),
+        ("(T)-x", &[Operator::Negate]),
+        ("(x)-1", &[Operator::Subtract]),
+        ("(unsigned long)&x", &[Operator::AddressOf]),
+        (
+            "(const struct S *)+p",
+            &[Operator::Star, Operator::UnaryPlus],
+        ),
+        ("(void (*)(int))&f", &[Operator::Star, Operator::AddressOf]),
+        ("sizeof(int)*2", &[Operator::Multiply]),
+        ("f(T)-1", &[Operator::Subtract]),
+        ("if (x) *p = 0;", &[Operator::Dereference, Operator::Assign]),
+        // 类型名之后的 * 是指针声明符，保留为 Star
+        ("int *p;", &[Operator::Star]),
+        ("char **argv;", &[Operator::Star, Operator::Star]),
+        (
+            "int *const *q = &x;",
+            &[
+                Operator::Star,
+                Operator::Star,
+                Operator::Assign,
+                Operator::AddressOf,
+            ],
+        ),
+        (
+            "T *x = y * z;",
+            &[Operator::Star, Operator::Assign, Operator::Multiply],
+        ),
+    ];
+    for (input, expected) in table {
+        let mut tokens: Vec<Token> = Lexer::new(input)
+            .map(|item| item.map(|(token, _)| token))
+            .collect::<Result<_, _>>()
+            .unwrap_or_else(|error| panic!("{:?} failed to lex: {}", input, error));
+        assert!(
+            !tokens.iter().any(|token| matches!(
+                token,
+                Token::Operators(
+                    Operator::Subtract
+                        | Operator::Negate
+                        | Operator::Multiply
+                        | Operator::Dereference
+                )
+            )),
+            "{:?}",
+            input
+        );
+        classify_operators(&mut tokens, &|name| name == "T");
+        let operators: Vec<Operator> = tokens
+            .into_iter()
+            .filter_map(|token| match token {
+                Token::Operators(operator) => Some(operator),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(operators, expected, "{:?}", input);
+    }
+}
@@ -0,0 +1,57 @@
+// 格式错误的常量输出覆盖整个常量的 Error token，未闭合的字符串在行尾结束，未结束的注释在开头报告
+use lexer::lexer::{LexError, Lexer, Span, Token};
+
+fn lex(source: &str) -> (Vec<(Token<'_>, &str)>, Vec<LexError>) {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    for item in Lexer::new(source) {
+        match item {
+            Ok((token, span)) => tokens.push((token, span.source_text(source))),
+            Err(error) => errors.push(error),
+        }
+    }
+    (tokens, errors)
+}
+
+#[test]
+fn malformed_literals_become_error_tokens() {
+    let table = [
+        ("0xg", "E0005"),
+        ("1e+", "E0003"),
+        ("08", "E0004"),
+        ("1.5q", "E0019"),
+        ("0b2", "E0006"),
+        ("99999999999999999999", "E0007"),
+        ("0x.8", "E0018"),
+        ("1u2", "E0016"),
+        ("''", "E0010"),
+    ];
+    for (literal, code) in table {
+        let source = format!("x = {} ;", literal);
+        let (tokens, errors) = lex(&source);
+        let spellings: Vec<&str> = tokens.iter().map(|(_, text)| *text).collect();
+        assert_eq!(spellings, ["x", "=", literal, ";", ""], "{:?}", literal);
+        assert_eq!(tokens[2].0, Token::Error, "{:?}", literal);
+        let codes: Vec<&str> = errors.iter().map(LexError::code).collect();
+        assert_eq!(codes, [code], "{:?}", literal);
+    }
+}
+
+#[test]
+fn unterminated_literals_and_comments_resynchronize() {
+    let source = "a = \"abc;\nb = 'c;\nc = 1; /* open\nd";
+    let (tokens, errors) = lex(source);
+    let spellings: Vec<&str> = tokens.iter().map(|(_, text)| *text).collect();
+    assert_eq!(
+        spellings,
+        ["a", "=", "\"abc;", "b", "=", "'c;", "c", "=", "1", ";", ""]
+    );
+    assert_eq!(
+        errors,
+        [
+            LexError::UnterminatedString(Span::new(0, 4, 9)),
+            LexError::UnterminatedChar(Span::new(0, 14, 17)),
+            LexError::UnterminatedComment(Span::new(0, 25, 27)),
+        ]
+    );
+}
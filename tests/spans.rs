@@ -0,0 +1,64 @@
+// 每个 token 都带有字节偏移的 Span，行列位置由 Span 推导
+use lexer::lexer::{Lexer, Span};
+
+#[test]
+fn spans_are_byte_offsets() {
+    let source = "int é = 1;\n  x\r\n\tfoo(\"s\")";
+    let spans: Vec<(Span, &str)> = Lexer::new(source)
+        .map(|item| {
+            let (_, span) = item.unwrap();
+            (span, span.source_text(source))
+        })
+        .collect();
+    let expected = [
+        (0, 3, "int"),
+        (4, 6, "é"), // 非 ASCII 字符按 UTF-8 字节计
+        (7, 8, "="),
+        (9, 10, "1"),
+        (10, 11, ";"),
+        (14, 15, "x"),
+        (18, 21, "foo"),
+        (21, 22, "("),
+        (22, 25, "\"s\""),
+        (25, 26, ")"),
+        (26, 26, ""), // EOF 位于输入末尾，长度为 0
+    ];
+    let expected: Vec<(Span, &str)> = expected
+        .iter()
+        .map(|&(start, end, text)| (Span::new(0, start, end), text))
+        .collect();
+    assert_eq!(spans, expected);
+}
+
+#[test]
+fn rows_and_columns_are_derived_from_spans() {
+    let source = "int é = 1;\n  x\r\n\tfoo(\"s\")";
+    let mut lexer = Lexer::new(source).with_file_id(3);
+    let (_, locations, errors) = lexer.lex().unwrap();
+    assert!(errors.is_empty(), "{:?}", errors);
+    let positions: Vec<(usize, usize)> = locations
+        .iter()
+        .map(|location| (location.row(), location.col()))
+        .collect();
+    // 列以字节计，从 1 开始；\r\n 与 \t 各占自己的字节
+    assert_eq!(
+        positions,
+        [
+            (1, 1),
+            (1, 5),
+            (1, 8),
+            (1, 10),
+            (1, 11),
+            (2, 3),
+            (3, 2),
+            (3, 5),
+            (3, 6),
+            (3, 9),
+            (3, 10)
+        ]
+    );
+    assert!(locations
+        .iter()
+        .all(|location| location.span().file_id == 3));
+    assert_eq!(lexer.location(Span::new(3, 12, 13)).row(), 2);
+}
@@ -0,0 +1,143 @@
+// 续行与三字符组：翻译后的拼写、物理行上的位置，以及 token 对输入的借用
+use lexer::lexer::{
+    Delimiter, Encoding, IntegerConstant, IntegerSuffix, IntegerType, Lexer, Number, Operator,
+    Radix, Span, StringLiteral, Token,
+};
+use std::borrow::Cow;
+
+#[test]
+fn only_spliced_tokens_are_copied() {
+    let source = "first = sec\\\nond + third;";
+    let identifiers: Vec<(Cow<'_, str>, Span)> = Lexer::new(source)
+        .filter_map(|item| match item.unwrap() {
+            (Token::Identifiers(name), span) => Some((name, span)),
+            _ => None,
+        })
+        .collect();
+    let names: Vec<&str> = identifiers.iter().map(|(name, _)| &**name).collect();
+    assert_eq!(names, ["first", "second", "third"]);
+    // 没有跨越续行的 token 仍然借用原始输入
+    let borrowed: Vec<bool> = identifiers
+        .iter()
+        .map(|(name, _)| matches!(name, Cow::Borrowed(_)))
+        .collect();
+    assert_eq!(borrowed, [true, false, true]);
+    assert_eq!(identifiers[1].1, Span::new(0, 8, 16));
+    assert_eq!(identifiers[2].1.source_text(source), "third");
+}
+
+// 开启三字符组时每个 token 与其在原始输入中的文本
+fn lex_trigraphs(source: &str) -> Vec<(Token<'_>, &str)> {
+    Lexer::new(source)
+        .with_trigraphs(true)
+        .map(|item| {
+            let (token, span) = item.unwrap();
+            (token, span.source_text(source))
+        })
+        .collect()
+}
+
+#[test]
+fn trigraphs_are_replaced_when_enabled() {
+    let table = [
+        ("??=", Token::Delimiters(Delimiter::Hash)),
+        ("??(", Token::Delimiters(Delimiter::LeftBracket)),
+        ("??)", Token::Delimiters(Delimiter::RightBracket)),
+        ("??<", Token::Delimiters(Delimiter::LeftBrace)),
+        ("??>", Token::Delimiters(Delimiter::RightBrace)),
+        ("??'", Token::Operators(Operator::BitwiseXor)),
+        ("??!", Token::Operators(Operator::BitwiseOr)),
+        ("??-", Token::Operators(Operator::BitwiseNot)),
+        ("??=??=", Token::Delimiters(Delimiter::HashHash)),
+    ];
+    for (spelling, expected) in table {
+        let source = format!("x {} y", spelling);
+        let tokens = lex_trigraphs(&source);
+        // Span 指向原始的三个字符
+        assert_eq!(tokens[1], (expected, spelling), "{:?}", spelling);
+        assert_eq!(tokens.len(), 4, "{:?}", spelling);
+    }
+
+    // 行首的 ??= 开始指令，??/ 加换行是续行
+    let tokens = lex_trigraphs("??=define X a??/\nb\n");
+    assert_eq!(
+        tokens,
+        [
+            (Token::Directive, "??="),
+            (Token::Identifiers("define".into()), "define"),
+            (Token::Identifiers("X".into()), "X"),
+            (Token::Identifiers("ab".into()), "a??/\nb"),
+            (Token::EndOfDirective, "\n"),
+            (Token::EOF, ""),
+        ]
+    );
+
+    // 默认不替换三字符组
+    let tokens: Vec<Token> = Lexer::new("??=").map(|item| item.unwrap().0).collect();
+    assert_eq!(
+        tokens,
+        [
+            Token::Delimiters(Delimiter::ConditionalOperator),
+            Token::Delimiters(Delimiter::ConditionalOperator),
+            Token::Operators(Operator::Assign),
+            Token::EOF
+        ]
+    );
+}
+
+#[test]
+fn spliced_tokens_keep_physical_locations() {
+    let number = |value| {
+        Token::Numbers(Number::Integer(IntegerConstant {
+            value,
+            radix: Radix::Decimal,
+            suffix: IntegerSuffix::None,
+            ty: IntegerType::Int,
+        }))
+    };
+    let string = |raw, value: &str| {
+        Token::Strings(StringLiteral {
+            encoding: Encoding::Plain,
+            raw: Cow::Borrowed(raw),
+            value: value.bytes().map(u32::from).collect(),
+        })
+    };
+    // 每个例子中第二个 token、它的行列以及在原始输入中的文本
+    let table = [
+        (
+            "x ab\\\ncd",
+            Token::Identifiers("abcd".into()),
+            (1, 3),
+            "ab\\\ncd",
+        ),
+        (
+            "x \"s\\\ntr\"",
+            string("\"str\"", "str"),
+            (1, 3),
+            "\"s\\\ntr\"",
+        ),
+        ("x 12\\\r\n34", number(1234), (1, 3), "12\\\r\n34"),
+        (
+            "x <\\\n<=",
+            Token::Operators(Operator::LeftShiftAssign),
+            (1, 3),
+            "<\\\n<=",
+        ),
+        ("x\\\n\\\n y", Token::Identifiers("y".into()), (3, 2), "y"),
+    ];
+    for (source, expected, position, text) in table {
+        let mut lexer = Lexer::new(source);
+        let (tokens, locations, errors) = lexer.lex().unwrap();
+        assert!(errors.is_empty(), "{:?}: {:?}", source, errors);
+        assert_eq!(tokens[1], expected, "{:?}", source);
+        let location = &locations[1];
+        assert_eq!((location.row(), location.col()), position, "{:?}", source);
+        assert_eq!(location.span().source_text(source), text);
+    }
+
+    // 续行之后的错误仍报告在物理行上
+    let mut lexer = Lexer::new("a\\\nb @");
+    let (_, _, errors) = lexer.lex().unwrap();
+    let location = lexer.location(errors[0].span());
+    assert_eq!((location.row(), location.col()), (2, 3));
+}
@@ -0,0 +1,127 @@
+// 按块读取时，跨越块边界的 token 应与整段输入得到的结果完全相同
+use lexer::lexer::{LexError, Lexer, Span, Token};
+use std::io::{self, BufRead, BufReader, Read};
+
+// 每次 fill_buf 最多只返回 size 个字节的 reader
+struct Chunked<'a> {
+    data: &'a [u8],
+    position: usize,
+    size: usize,
+}
+
+impl Read for Chunked<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let chunk = self.fill_buf()?;
+        let len = chunk.len().min(buf.len());
+        buf[..len].copy_from_slice(&chunk[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for Chunked<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let end = (self.position + self.size).min(self.data.len());
+        Ok(&self.data[self.position..end])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position += amt;
+    }
+}
+
+type Lexed = Vec<Result<(Token<'static>, Span), LexError>>;
+
+fn collect(lexer: Lexer<'_>) -> Lexed {
+    lexer
+        .map(|item| item.map(|(token, span)| (token.into_owned(), span)))
+        .collect()
+}
+
+#[test]
+fn chunked_reader_matches_str_input() {
+    let sources = [
+        "x = \"a string with \\\"escapes\\\"\" + 'c';\n",
+        "a /* block\ncomment */ b // line comment\nc",
+        "a <<= b >>= c ... d -> e ## f %:%: g <: h :>",
+        "long_name\\\nspliced = 12\\\n34 + 0x1.8p3f;\r\nint \\\r\nx;",
+        "#include <stdio.h>\n#define F(x) #x\nnaïve = u8\"ü\";",
+        "s = \"unterminated\nt = 'ab\n/* never closed",
+    ];
+    for source in sources {
+        let expected = collect(Lexer::new(source));
+        for size in [1, 2] {
+            let reader = Chunked {
+                data: source.as_bytes(),
+                position: 0,
+                size,
+            };
+            assert_eq!(
+                collect(Lexer::from_reader(reader)),
+                expected,
+                "{:?} with {}-byte chunks",
+                source,
+                size
+            );
+        }
+    }
+}
+
+#[test]
+fn long_comments_do_not_grow_the_buffer() {
+    // 未结束的长注释：报告错误时缓冲区中只剩下注释末尾的一小段
+    let body = "comment text ".repeat(100_000);
+    let source = format!("a // {}\nb /* {}", body, body);
+    let reader = Chunked {
+        data: source.as_bytes(),
+        position: 0,
+        size: 4096,
+    };
+    let mut lexer = Lexer::from_reader(reader);
+    let mut tokens = Vec::new();
+    while let Some(item) = lexer.next_token() {
+        match item {
+            Ok((token, _)) => tokens.push(token.into_owned()),
+            Err(error) => {
+                assert_eq!(error.code(), "E0023");
+                assert!(
+                    lexer.buffered_len() < 256 * 1024,
+                    "{}",
+                    lexer.buffered_len()
+                );
+            }
+        }
+    }
+    assert_eq!(
+        tokens,
+        [
+            Token::Identifiers("a".into()),
+            Token::Identifiers("b".into()),
+            Token::EOF
+        ]
+    );
+}
+
+#[test]
+fn tokens_straddle_buf_reader_chunks() {
+    // 注释与字符串都跨越好几个 4 字节的块
+    let source = "a /* a comment spanning chunks */ \"a string spanning chunks\" b\n";
+    let reader = BufReader::with_capacity(4, source.as_bytes());
+    let lexed = collect(Lexer::from_reader(reader));
+    assert_eq!(lexed, collect(Lexer::new(source)));
+    let tokens: Vec<(Token, &str)> = lexed
+        .into_iter()
+        .map(|item| {
+            let (token, span) = item.unwrap();
+            (token, span.source_text(source))
+        })
+        .collect();
+    let texts: Vec<&str> = tokens.iter().map(|(_, text)| *text).collect();
+    assert_eq!(texts, ["a", "\"a string spanning chunks\"", "b", ""]);
+    match &tokens[1].0 {
+        Token::Strings(literal) => {
+            assert_eq!(literal.to_string_lossy(), "a string spanning chunks")
+        }
+        other => panic!("{:?}", other),
+    }
+}
@@ -0,0 +1,54 @@
+// 制表符、\v、\f、\r 都是空白，\r\n 算作一个换行
+use lexer::lexer::{Lexer, Token};
+use lexer::preprocessor::Preprocessor;
+
+#[test]
+fn tabs_and_crlf_separate_tokens() {
+    let source = "int\tx;\r\n\tif (x)\x0b\x0c{\r\n\t\treturn 1;\r}\r\n";
+    let mut lexer = Lexer::new(source);
+    let (tokens, locations, errors) = lexer.lex().unwrap();
+    assert!(errors.is_empty(), "{:?}", errors);
+    let spellings: Vec<&str> = locations
+        .iter()
+        .map(|location| location.span().source_text(source))
+        .collect();
+    assert_eq!(
+        spellings,
+        ["int", "x", ";", "if", "(", "x", ")", "{", "return", "1", ";", "}", ""]
+    );
+    let rows: Vec<usize> = locations.iter().map(|location| location.row()).collect();
+    assert_eq!(rows, [1, 1, 1, 2, 2, 2, 2, 2, 3, 3, 3, 3, 4]);
+    assert_eq!(tokens.last(), Some(&Token::EOF));
+}
+
+#[test]
+fn crlf_ends_directives() {
+    let mut preprocessor = Preprocessor::new();
+    let (tokens, errors) =
+        preprocessor.preprocess("crlf.c", "#define A 1\r\n\t#define B A + 2\r\nB\r\n");
+    assert!(errors.is_empty(), "{:?}", errors);
+    let spellings: Vec<&str> = tokens.iter().map(|token| token.spelling.as_str()).collect();
+    assert_eq!(spellings, ["1", "+", "2", ""]);
+    let rows: Vec<Option<usize>> = tokens
+        .iter()
+        .map(|token| token.expanded_from.as_ref().map(|location| location.row()))
+        .collect();
+    assert_eq!(rows, [Some(3), Some(3), Some(3), None]);
+}
+
+#[test]
+fn partial_preprocessing_keeps_crlf_files_intact() {
+    let mut preprocessor = Preprocessor::new();
+    preprocessor.define("FOO", "1");
+    let (output, errors) =
+        preprocessor.preprocess_partial("crlf.c", "#ifdef FOO\r\na\r\n#endif\r\n");
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(output, "a\r\n");
+
+    let mut preprocessor = Preprocessor::new();
+    preprocessor.undefine("FOO");
+    let source = "#if BAR\r\n\tx\r\n#elif defined(FOO)\r\n\ty\r\n#else\r\n\tz\r\n#endif\r\n";
+    let (output, errors) = preprocessor.preprocess_partial("crlf.c", source);
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(output, "#if BAR\r\n\tx\r\n#else\r\n\tz\r\n#endif\r\n");
+}